@@ -0,0 +1,112 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Parsing of the `#[slog(...)]` attributes accepted by the derive macros.
+
+use crate::double_speak::DoubleSpeak;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Attribute;
+use syn::Data;
+use syn::DeriveInput;
+use syn::LitStr;
+
+/// Options given via `#[slog(...)]` attributes on the type being derived.
+#[derive(Default)]
+pub(crate) struct TypeAttrs {
+    /// `#[slog(key = "...")]`: the key used by the generated `slog::KV` impl.
+    pub(crate) key: Option<LitStr>,
+    /// `#[slog(root_cause)]`: also log `{key}.root` and `{key}.depth`.
+    pub(crate) root_cause: bool,
+    /// `#[slog(variant_key = "...")]`: the key under which the generated
+    /// `slog::KV` impl logs the name of the enum variant.
+    pub(crate) variant_key: Option<LitStr>,
+    /// `#[slog(double_speak = "deny" | "warn" | "allow")]`: how to report
+    /// messages that interpolate their own source.
+    pub(crate) double_speak: DoubleSpeak,
+    /// `#[slog(format = "...")]`: the format generated by
+    /// `#[derive(SlogError)]`.
+    pub(crate) format: Option<LitStr>,
+    /// `#[slog(level = "...")]`: the level reported by the derived
+    /// `SlogErrorLevel` impl for variants without their own.
+    pub(crate) level: Option<LitStr>,
+    /// `#[slog(no_serialize)]`: don't implement `serde::Serialize` or
+    /// `slog::SerdeValue` for the type itself.
+    pub(crate) no_serialize: Option<syn::Path>,
+}
+
+impl TypeAttrs {
+    pub(crate) fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut out = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("slog")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("key") {
+                    out.key = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("root_cause") {
+                    out.root_cause = true;
+                    Ok(())
+                } else if meta.path.is_ident("variant_key") {
+                    out.variant_key = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("level") {
+                    out.level = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("no_serialize") {
+                    out.no_serialize = Some(meta.path);
+                    Ok(())
+                } else if meta.path.is_ident("format") {
+                    out.format = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("double_speak") {
+                    out.double_speak =
+                        DoubleSpeak::parse(&meta.value()?.parse()?)?;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported slog attribute"))
+                }
+            })?;
+        }
+        Ok(out)
+    }
+
+    /// Returns the option setters to chain onto the adapter used by the
+    /// generated `slog::KV` impl.
+    pub(crate) fn kv_options(&self) -> TokenStream {
+        let key = self.key.as_ref().map(|key| quote!(.with_key(#key)));
+        let root_cause =
+            self.root_cause.then(|| quote!(.emit_root_cause(true)));
+        quote!(#key #root_cause)
+    }
+
+    /// Returns code for the generated `slog::KV` impl that logs the name of
+    /// the variant of `input` (which must be an enum) under `variant_key`, if
+    /// one was given.
+    pub(crate) fn emit_variant(
+        &self,
+        input: &DeriveInput,
+    ) -> syn::Result<TokenStream> {
+        let Some(key) = &self.variant_key else {
+            return Ok(TokenStream::new());
+        };
+        let Data::Enum(data) = &input.data else {
+            return Err(syn::Error::new_spanned(
+                key,
+                "`variant_key` is only supported on enums",
+            ));
+        };
+        let arms = data.variants.iter().map(|variant| {
+            let ident = &variant.ident;
+            let name = ident.to_string();
+            quote!(Self::#ident { .. } => #name,)
+        });
+        Ok(quote! {
+            let variant = match self {
+                #(#arms)*
+            };
+            #[allow(clippy::useless_conversion)]
+            serializer.emit_str(::std::convert::Into::into(#key), variant)?;
+        })
+    }
+}
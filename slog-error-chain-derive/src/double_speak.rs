@@ -0,0 +1,245 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Detection of "double-speak": `thiserror` messages that interpolate the
+//! error's own source, which is then printed twice when the chain is logged
+//! (e.g., `opening file: not found: not found`).
+
+use crate::type_names::is_transparent;
+use crate::type_names::source_member;
+use proc_macro2::Span;
+use proc_macro2::TokenStream;
+use proc_macro2::TokenTree;
+use quote::quote_spanned;
+use syn::Attribute;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Fields;
+use syn::LitStr;
+use syn::Member;
+
+/// How to report double-speak, per `#[slog(double_speak = "...")]`.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum DoubleSpeak {
+    /// Fail to compile.
+    Deny,
+    /// Emit a (deprecation) warning (the default).
+    #[default]
+    Warn,
+    /// Don't check for double-speak.
+    Allow,
+}
+
+impl DoubleSpeak {
+    pub(crate) fn parse(value: &LitStr) -> syn::Result<Self> {
+        match value.value().as_str() {
+            "deny" => Ok(Self::Deny),
+            "warn" => Ok(Self::Warn),
+            "allow" => Ok(Self::Allow),
+            _ => Err(syn::Error::new_spanned(
+                value,
+                "expected `double_speak = \"deny\"`, `\"warn\"`, or `\"allow\"`",
+            )),
+        }
+    }
+}
+
+/// Checks the `#[error(...)]` messages of `input` for double-speak, returning
+/// an error or code producing warnings, per `severity`.
+pub(crate) fn check_double_speak(
+    input: &DeriveInput,
+    severity: DoubleSpeak,
+) -> syn::Result<TokenStream> {
+    if severity == DoubleSpeak::Allow {
+        return Ok(TokenStream::new());
+    }
+    let found = match &input.data {
+        Data::Struct(data) => {
+            find_double_speak(&data.fields, &input.attrs).into_iter().collect()
+        }
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .filter_map(|variant| {
+                find_double_speak(&variant.fields, &variant.attrs)
+            })
+            .collect(),
+        Data::Union(_) => Vec::new(),
+    };
+
+    let mut errors = found.iter().map(|(span, source)| {
+        let message = format!(
+            "error message interpolates its source `{}`, whose message will \
+             be printed twice when the error chain is logged; remove it from \
+             the message, or allow this with \
+             `#[slog(double_speak = \"allow\")]`",
+            member_name(source),
+        );
+        (*span, message)
+    });
+    match severity {
+        DoubleSpeak::Deny => match errors.next() {
+            Some((span, message)) => {
+                let mut error = syn::Error::new(span, message);
+                for (span, message) in errors {
+                    error.combine(syn::Error::new(span, message));
+                }
+                Err(error)
+            }
+            None => Ok(TokenStream::new()),
+        },
+        DoubleSpeak::Warn => Ok(errors
+            .map(|(span, message)| {
+                quote_spanned! {span=>
+                    const _: () = {
+                        #[deprecated(note = #message)]
+                        struct DoubleSpeak;
+                        let _ = DoubleSpeak;
+                    };
+                }
+            })
+            .collect()),
+        DoubleSpeak::Allow => unreachable!(),
+    }
+}
+
+/// If the `#[error(...)]` message among `attrs` interpolates the source field
+/// among `fields`, returns the span of the message and that field.
+fn find_double_speak(
+    fields: &Fields,
+    attrs: &[Attribute],
+) -> Option<(Span, Member)> {
+    if is_transparent(attrs) {
+        return None;
+    }
+    let source = source_member(fields, attrs)?;
+    let attr = attrs.iter().find(|attr| attr.path().is_ident("error"))?;
+    let tokens = attr.meta.require_list().ok()?.tokens.clone();
+    let mut tokens = tokens.into_iter();
+    let Some(TokenTree::Literal(literal)) = tokens.next() else {
+        return None;
+    };
+    let message =
+        syn::parse2::<LitStr>(TokenTree::Literal(literal).into()).ok()?;
+
+    let args = tokens.collect::<TokenStream>();
+    let named_args = named_args(args.clone());
+    let mut members = placeholders(&message.value())
+        .into_iter()
+        .filter(|name| !named_args.contains(name))
+        .collect::<Vec<_>>();
+    shorthand_members(args, &mut members);
+
+    members
+        .iter()
+        .any(|name| *name == member_name(&source))
+        .then(|| (message.span(), source))
+}
+
+/// Returns the string used to refer to `member` in a `thiserror` message.
+fn member_name(member: &Member) -> String {
+    match member {
+        Member::Named(ident) => ident.to_string(),
+        Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
+/// Returns the explicitly named arguments (`{name}`) of format string
+/// `message`, ignoring escaped braces and format specs.
+fn placeholders(message: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut rest = message;
+    while let Some(start) = rest.find(['{', '}']) {
+        let brace = &rest[start..];
+        if brace.starts_with("{{") || brace.starts_with("}}") {
+            rest = &brace[2..];
+            continue;
+        }
+        if let Some(after) = brace.strip_prefix('}') {
+            rest = after;
+            continue;
+        }
+        let Some(end) = brace.find('}') else {
+            break;
+        };
+        let arg = brace[1..end].split(':').next().unwrap_or_default().trim();
+        if !arg.is_empty() {
+            out.push(arg.trim_start_matches("r#").to_string());
+        }
+        rest = &brace[end + 1..];
+    }
+    out
+}
+
+/// Returns the names given to arguments of the form `name = value` among
+/// `args`, the tokens following the message.
+fn named_args(args: TokenStream) -> Vec<String> {
+    let tokens = args.into_iter().collect::<Vec<_>>();
+    tokens
+        .windows(3)
+        .filter_map(|window| match window {
+            [TokenTree::Punct(comma), TokenTree::Ident(name), TokenTree::Punct(eq)]
+                if comma.as_char() == ',' && eq.as_char() == '=' =>
+            {
+                Some(name.to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Pushes onto `members` the fields referred to by `thiserror`'s `.field`
+/// shorthand within `args`: a `.` not preceded by an operand, followed by a
+/// field name or index.
+fn shorthand_members(args: TokenStream, members: &mut Vec<String>) {
+    let mut after_operand = false;
+    let mut tokens = args.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match &token {
+            TokenTree::Punct(punct) if punct.as_char() == '.' => {
+                if !after_operand {
+                    match tokens.peek() {
+                        Some(TokenTree::Ident(ident)) => {
+                            members.push(ident.to_string())
+                        }
+                        Some(TokenTree::Literal(literal)) => {
+                            members.push(literal.to_string())
+                        }
+                        _ => {}
+                    }
+                }
+                after_operand = false;
+            }
+            TokenTree::Punct(_) => after_operand = false,
+            TokenTree::Group(group) => {
+                shorthand_members(group.stream(), members);
+                after_operand = true;
+            }
+            TokenTree::Ident(_) | TokenTree::Literal(_) => after_operand = true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::quote;
+
+    #[test]
+    fn parse_placeholders() {
+        assert_eq!(
+            placeholders("{a} {{b}} {c:?} {0} {} {:>8} }}{r#d}"),
+            ["a", "c", "0", "d"]
+        );
+    }
+
+    #[test]
+    fn parse_args() {
+        let args = quote!(, .path.display(), x = .source, f(.0), self.y);
+        assert_eq!(named_args(args.clone()), ["x"]);
+        let mut members = Vec::new();
+        shorthand_members(args, &mut members);
+        assert_eq!(members, ["path", "source", "0"]);
+    }
+}
@@ -0,0 +1,201 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Generation of `slog_error_chain::ErrorFields` implementations from fields
+//! marked `#[slog(field)]`.
+
+use crate::type_names::members;
+use proc_macro2::Ident;
+use proc_macro2::TokenStream;
+use proc_macro2::TokenTree;
+use quote::format_ident;
+use quote::quote;
+use quote::quote_spanned;
+use quote::ToTokens;
+use syn::ext::IdentExt;
+use syn::parse_quote;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Fields;
+use syn::LitStr;
+use syn::Member;
+use syn::Type;
+use syn::WherePredicate;
+
+/// A field marked `#[slog(field)]` or `#[slog(field(rename = "..."))]`.
+struct LoggedField {
+    member: Member,
+    /// The key under which the field is logged.
+    name: LitStr,
+    ty: Type,
+}
+
+/// Returns an implementation of `ErrorFields` for `input`, or `None` if none
+/// of its fields are marked `#[slog(field)]`.
+pub(crate) fn impl_error_fields(
+    input: &DeriveInput,
+) -> syn::Result<Option<TokenStream>> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = logged_fields(&data.fields)?;
+            if fields.is_empty() {
+                return Ok(None);
+            }
+            let emits = fields.iter().map(|field| {
+                let member = &field.member;
+                emit_field(quote!(&self.#member), &field.ty, &field.name)
+            });
+            quote!(#(#emits)*)
+        }
+        Data::Enum(data) => {
+            let mut any = false;
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let fields = logged_fields(&variant.fields)?;
+                any |= !fields.is_empty();
+                let bindings = (0..fields.len())
+                    .map(|i| format_ident!("__slog_field_{}", i))
+                    .collect::<Vec<_>>();
+                let members = fields.iter().map(|field| &field.member);
+                let emits =
+                    fields.iter().zip(&bindings).map(|(field, binding)| {
+                        emit_field(quote!(#binding), &field.ty, &field.name)
+                    });
+                arms.push(quote! {
+                    Self::#ident { #(#members: #bindings,)* .. } => { #(#emits)* }
+                });
+            }
+            if !any {
+                return Ok(None);
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => return Ok(None),
+    };
+
+    Ok(Some(quote! {
+        impl #impl_generics ::slog_error_chain::ErrorFields for #name #ty_generics #where_clause {
+            #[allow(clippy::useless_conversion)]
+            fn serialize_fields(
+                &self,
+                record: &::slog::Record,
+                serializer: &mut dyn ::slog::Serializer,
+            ) -> ::slog::Result {
+                #body
+                ::std::result::Result::Ok(())
+            }
+        }
+    }))
+}
+
+/// Returns the bounds required of the generic parameters of `input` for its
+/// fields marked `#[slog(field)]` to be logged: as `serde` does, that the
+/// types of those that refer to type parameters implement `slog::Value`.
+/// (Other types are checked where they're logged.)
+pub(crate) fn field_bounds(
+    input: &DeriveInput,
+) -> syn::Result<Vec<WherePredicate>> {
+    let params = input
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect::<Vec<_>>();
+    if params.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut fields = Vec::new();
+    match &input.data {
+        Data::Struct(data) => fields.extend(logged_fields(&data.fields)?),
+        Data::Enum(data) => {
+            for variant in &data.variants {
+                fields.extend(logged_fields(&variant.fields)?);
+            }
+        }
+        Data::Union(_) => {}
+    }
+    Ok(fields
+        .into_iter()
+        .map(|field| field.ty)
+        .filter(|ty| mentions_any(ty.to_token_stream(), &params))
+        .map(|ty| parse_quote!(#ty: ::slog::Value))
+        .collect())
+}
+
+/// Returns whether `tokens` (a type) refer to any of `idents`.
+fn mentions_any(tokens: TokenStream, idents: &[Ident]) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => idents.contains(&ident),
+        TokenTree::Group(group) => mentions_any(group.stream(), idents),
+        TokenTree::Punct(_) | TokenTree::Literal(_) => false,
+    })
+}
+
+/// Returns code logging `value` (a reference to a field of type `ty`) under
+/// `name` via its `slog::Value` implementation.
+fn emit_field(value: TokenStream, ty: &Type, name: &LitStr) -> TokenStream {
+    quote_spanned! {ty.span()=>
+        ::slog::Value::serialize(
+            #value,
+            record,
+            ::std::convert::Into::into(#name),
+            serializer,
+        )?;
+    }
+}
+
+/// Returns the fields marked `#[slog(field)]` among `fields`.
+fn logged_fields(fields: &Fields) -> syn::Result<Vec<LoggedField>> {
+    let mut out = Vec::new();
+    for (field, member) in fields.iter().zip(members(fields)) {
+        for attr in
+            field.attrs.iter().filter(|attr| attr.path().is_ident("slog"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if !meta.path.is_ident("field") {
+                    return Err(meta.error("unsupported slog field attribute"));
+                }
+                let mut rename = None;
+                if !meta.input.is_empty() && !meta.input.peek(syn::Token![,]) {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("rename") {
+                            rename = Some(meta.value()?.parse::<LitStr>()?);
+                            Ok(())
+                        } else {
+                            Err(meta.error("unsupported slog field option"))
+                        }
+                    })?;
+                }
+                let name = match (rename, &field.ident) {
+                    (Some(name), _) => name,
+                    (None, Some(ident)) => {
+                        LitStr::new(&ident.unraw().to_string(), ident.span())
+                    }
+                    (None, None) => {
+                        return Err(meta.error(
+                            "`#[slog(field)]` on a tuple field requires \
+                             `#[slog(field(rename = \"...\"))]`",
+                        ));
+                    }
+                };
+                out.push(LoggedField {
+                    member: member.clone(),
+                    name,
+                    ty: field.ty.clone(),
+                });
+                Ok(())
+            })?;
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,91 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Generation of `slog_error_chain::SlogErrorLevel` implementations from
+//! `#[slog(level = "...")]` attributes.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Attribute;
+use syn::Data;
+use syn::DeriveInput;
+use syn::LitStr;
+
+/// Returns the path of the `slog::Level` named by `value`.
+pub(crate) fn parse_level(value: &LitStr) -> syn::Result<TokenStream> {
+    match value.value().as_str() {
+        "critical" | "crit" => Ok(quote!(::slog::Level::Critical)),
+        "error" => Ok(quote!(::slog::Level::Error)),
+        "warning" | "warn" => Ok(quote!(::slog::Level::Warning)),
+        "info" => Ok(quote!(::slog::Level::Info)),
+        "debug" => Ok(quote!(::slog::Level::Debug)),
+        "trace" => Ok(quote!(::slog::Level::Trace)),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            "expected `level = \"critical\"`, `\"error\"`, `\"warn\"`, \
+             `\"info\"`, `\"debug\"`, or `\"trace\"`",
+        )),
+    }
+}
+
+/// Returns an implementation of `SlogErrorLevel` for `input`, reporting the
+/// level given by `#[slog(level = "...")]` on each variant, or otherwise
+/// `default` (from the type's own attribute), or `slog::Level::Error`.
+pub(crate) fn impl_slog_error_level(
+    input: &DeriveInput,
+    default: Option<&LitStr>,
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    let default = match default {
+        Some(level) => parse_level(level)?,
+        None => quote!(::slog::Level::Error),
+    };
+
+    let body = match &input.data {
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let level = match variant_level(&variant.attrs)? {
+                    Some(level) => parse_level(&level)?,
+                    None => default.clone(),
+                };
+                arms.push(quote!(Self::#ident { .. } => #level,));
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Struct(_) | Data::Union(_) => default,
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::slog_error_chain::SlogErrorLevel for #name #ty_generics #where_clause {
+            fn log_level(&self) -> ::slog::Level {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns the level given by `#[slog(level = "...")]` among the `attrs` of a
+/// variant, the only option supported there.
+fn variant_level(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    let mut level = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("slog")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("level") {
+                level = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported slog variant attribute"))
+            }
+        })?;
+    }
+    Ok(level)
+}
@@ -13,78 +13,278 @@
 //! `serde::Serialize` that will log errors as an array of strings (one element
 //! for each cause), if the logger in use itself supports nested values via
 //! `serde`.
+//!
+//! Similarly, the `SlogObjectError` macro logs errors as an array of objects
+//! carrying each cause's message and type, via
+//! `slog_error_chain::ObjectErrorChain`.
+//!
+//! The `SlogError` macro generates any of these, selected by
+//! `#[slog(format = "...")]` on the type: `"inline"` (the default), `"array"`,
+//! `"flat"` (each cause logged under its own key, via
+//! `slog_error_chain::FlatErrorChain`), or `"object"`. The `"array"` and
+//! `"object"` formats require the `nested-values` feature.
+//!
+//! Every macro also implements `slog_error_chain::ErrorTypeNames` for the
+//! type, which reports the types of the error and its (statically known)
+//! causes.
+//!
+//! The `SlogArrayError` and `SlogObjectError` macros also implement
+//! `serde::Serialize` for the type, which conflicts with any existing
+//! implementation. With `#[slog(no_serialize)]`, they implement neither
+//! `serde::Serialize` nor `slog::SerdeValue` for the type itself: the
+//! generated `slog::Value` impl logs the type through the (`SerdeValue`)
+//! adapter instead, which produces the same output, and the type's own
+//! `Serialize` impl (if any) is left untouched. The other formats implement
+//! neither trait, so `#[slog(no_serialize)]` is an error there.
+//!
+//! All macros accept an optional `#[slog(key = "...")]` attribute on the type
+//! to choose the key used when the error is logged without an explicit key
+//! (by default, `"error"`), and `#[slog(root_cause)]` to additionally log the
+//! message of the innermost cause as `{key}.root` and the length of the chain
+//! as `{key}.depth`.
+//!
+//! Every macro also checks the `thiserror` `#[error(...)]` messages of the
+//! type for "double-speak": a message that interpolates the error's own source
+//! (the field marked `#[source]` or `#[from]`, or named `source`), which would
+//! be printed twice when the chain is logged. This is reported as a
+//! (deprecation) warning by default; `#[slog(double_speak = "deny")]` makes it
+//! a compile error instead, and `#[slog(double_speak = "allow")]` disables the
+//! check.
+//!
+//! On enums, `#[slog(variant_key = "...")]` additionally logs the name of the
+//! error's variant (e.g., `OpeningFile`) under the given key.
+//!
+//! Fields of the error (of a struct, or of any enum variant) marked
+//! `#[slog(field)]` are logged by the generated `slog::KV` impl as
+//! `{key}.0.{field}` via their own `slog::Value` impls, through a derived
+//! `slog_error_chain::ErrorFields` impl. `#[slog(field(rename = "..."))]` logs
+//! a field under a different name, and is required for tuple fields.
+//!
+//! Every macro also implements `slog_error_chain::SlogErrorLevel`, reporting
+//! the level given by `#[slog(level = "...")]` on the error's enum variant, or
+//! else on the type, or else `slog::Level::Error`. The level is one of
+//! `"critical"`, `"error"`, `"warn"`, `"info"`, `"debug"`, or `"trace"`.
+//!
+//! The generated implementations require that the type implement
+//! `std::error::Error` (and that the types of logged fields that refer to type
+//! parameters implement `slog::Value`), so that for generic types, they apply
+//! whenever the type is an error. Deriving any of the macros for a type that
+//! isn't an error is a compile error pointing at the type.
 
+use proc_macro2::TokenStream;
 use quote::quote;
+use quote::quote_spanned;
 use syn::parse_macro_input;
+use syn::parse_quote_spanned;
 use syn::DeriveInput;
+use syn::LitStr;
+use syn::WherePredicate;
+
+mod attrs;
+mod double_speak;
+mod fields;
+mod level;
+mod type_names;
+
+use attrs::TypeAttrs;
+use double_speak::check_double_speak;
+use fields::field_bounds;
+use fields::impl_error_fields;
+use level::impl_slog_error_level;
+use type_names::impl_error_type_names;
 
 /// Derives an implementation of `slog::Value` that delegates to
 /// `slog_error_chain::InlineErrorChain`.
-#[proc_macro_derive(SlogInlineError)]
+#[proc_macro_derive(SlogInlineError, attributes(slog))]
 pub fn derive_slog_inline_error(
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = input.ident;
-    let (impl_generics, ty_generics, where_clause) =
-        input.generics.split_for_impl();
-
-    let expanded = quote! {
-        impl #impl_generics ::slog::KV for #name #ty_generics #where_clause {
-            fn serialize(
-                &self,
-                record: &::slog::Record,
-                serializer: &mut dyn ::slog::Serializer,
-            ) -> ::slog::Result {
-                ::slog_error_chain::InlineErrorChain::new(self).serialize(
-                    record,
-                    serializer,
-                )
-            }
-        }
-        impl #impl_generics ::slog::Value for #name #ty_generics #where_clause {
-            fn serialize(
-                &self,
-                record: &::slog::Record,
-                key: ::slog::Key,
-                serializer: &mut dyn ::slog::Serializer,
-            ) -> ::slog::Result {
-                ::slog_error_chain::InlineErrorChain::new(self).serialize(
-                    record,
-                    key,
-                    serializer,
-                )
-            }
-        }
-    };
-
-    proc_macro::TokenStream::from(expanded)
+    derive(&input, Some(Format::Inline)).into()
 }
 
 /// Derives an implementation of `slog::SerdeValue` (and `slog::Value` and
 /// `serde::Serialize`, which are required to implement `slog::SerdeValue`) that
 /// delegates to `slog_error_chain::ArrayErrorChain`.
 #[cfg(feature = "nested-values")]
-#[proc_macro_derive(SlogArrayError)]
+#[proc_macro_derive(SlogArrayError, attributes(slog))]
 pub fn derive_slog_array_error(
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = input.ident;
+    derive(&input, Some(Format::Array)).into()
+}
+
+/// Derives an implementation of `slog::SerdeValue` (and `slog::Value` and
+/// `serde::Serialize`, which are required to implement `slog::SerdeValue`) that
+/// delegates to `slog_error_chain::ObjectErrorChain`, recording the types of the
+/// errors in the chain via the derived `slog_error_chain::ErrorTypeNames`
+/// implementation.
+#[cfg(feature = "nested-values")]
+#[proc_macro_derive(SlogObjectError, attributes(slog))]
+pub fn derive_slog_object_error(
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(&input, Some(Format::Object)).into()
+}
+
+/// Derives the implementations of whichever of the other macros is selected by
+/// `#[slog(format = "...")]` (by default, those of `SlogInlineError`).
+#[proc_macro_derive(SlogError, attributes(slog))]
+pub fn derive_slog_error(
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(&input, None).into()
+}
+
+/// The adapter to which the derived implementations delegate.
+#[derive(Clone, Copy)]
+enum Format {
+    Inline,
+    Flat,
+    #[cfg(feature = "nested-values")]
+    Array,
+    #[cfg(feature = "nested-values")]
+    Object,
+}
+
+impl Format {
+    fn parse(value: &LitStr) -> syn::Result<Self> {
+        let format = value.value();
+        match format.as_str() {
+            "inline" => return Ok(Self::Inline),
+            "flat" => return Ok(Self::Flat),
+            #[cfg(feature = "nested-values")]
+            "array" => return Ok(Self::Array),
+            #[cfg(feature = "nested-values")]
+            "object" => return Ok(Self::Object),
+            _ => {}
+        }
+        let message = match format.as_str() {
+            "array" | "object" => format!(
+                "`format = \"{format}\"` requires the `nested-values` feature \
+                 of `slog-error-chain`"
+            ),
+            _ => "expected `format = \"inline\"`, `\"array\"`, `\"flat\"`, \
+                  or `\"object\"`"
+                .to_string(),
+        };
+        Err(syn::Error::new_spanned(value, message))
+    }
+
+    /// Returns the path of the adapter type, and the constructor used when the
+    /// type has no `ErrorFields`.
+    fn adapter(self) -> (TokenStream, TokenStream) {
+        match self {
+            Self::Inline => {
+                (quote!(::slog_error_chain::InlineErrorChain), quote!(new))
+            }
+            Self::Flat => {
+                (quote!(::slog_error_chain::FlatErrorChain), quote!(new))
+            }
+            #[cfg(feature = "nested-values")]
+            Self::Array => {
+                (quote!(::slog_error_chain::ArrayErrorChain), quote!(new))
+            }
+            #[cfg(feature = "nested-values")]
+            Self::Object => (
+                quote!(::slog_error_chain::ObjectErrorChain),
+                quote!(from_typed),
+            ),
+        }
+    }
+}
+
+/// Returns the derived implementations for `input` in `format`, or, if the
+/// format isn't fixed by the macro, that given by its attributes.
+fn derive(input: &DeriveInput, format: Option<Format>) -> TokenStream {
+    expand(input, format).unwrap_or_else(|err| err.to_compile_error())
+}
+
+fn expand(
+    input: &DeriveInput,
+    format: Option<Format>,
+) -> syn::Result<TokenStream> {
+    let attrs = TypeAttrs::parse(&input.attrs)?;
+    let format = match (format, &attrs.format) {
+        (None, None) => Format::Inline,
+        (None, Some(format)) => Format::parse(format)?,
+        (Some(format), None) => format,
+        (Some(_), Some(format)) => {
+            return Err(syn::Error::new_spanned(
+                format,
+                "`format` is only supported by `#[derive(SlogError)]`",
+            ));
+        }
+    };
+    if let (Format::Inline | Format::Flat, Some(no_serialize)) =
+        (format, &attrs.no_serialize)
+    {
+        return Err(syn::Error::new_spanned(
+            no_serialize,
+            "`no_serialize` is only supported by the `array` and `object` \
+             formats, which implement `serde::Serialize`",
+        ));
+    }
+    let double_speak = check_double_speak(input, attrs.double_speak)?;
+    let (input, assert_error) = with_bounds(input)?;
+    let input = &input;
+    let error_fields = impl_error_fields(input)?;
+    let emit_variant = attrs.emit_variant(input)?;
+    let level = impl_slog_error_level(input, attrs.level.as_ref())?;
+    let kv_options = attrs.kv_options();
+    let type_names = impl_error_type_names(input);
+    let (adapter, new) = format.adapter();
+    let constructor = match &error_fields {
+        Some(_) => quote!(with_fields),
+        None => new.clone(),
+    };
+    let name = &input.ident;
     let (impl_generics, ty_generics, where_clause) =
         input.generics.split_for_impl();
 
-    let expanded = quote! {
+    let serde_value = match format {
+        Format::Inline | Format::Flat => TokenStream::new(),
+        #[cfg(feature = "nested-values")]
+        _ if attrs.no_serialize.is_some() => TokenStream::new(),
+        #[cfg(feature = "nested-values")]
+        Format::Array => serde_value(
+            input,
+            &adapter,
+            &new,
+            quote!(::slog_error_chain::OwnedErrorChain::new(self)),
+        ),
+        #[cfg(feature = "nested-values")]
+        Format::Object => serde_value(
+            input,
+            &adapter,
+            &new,
+            quote!(::slog_error_chain::OwnedObjectErrorChain::from_typed(self)),
+        ),
+    };
+
+    Ok(quote! {
+        #assert_error
+        #double_speak
+        #type_names
+        #error_fields
+        #level
+
         impl #impl_generics ::slog::KV for #name #ty_generics #where_clause {
             fn serialize(
                 &self,
                 record: &::slog::Record,
                 serializer: &mut dyn ::slog::Serializer,
             ) -> ::slog::Result {
-                ::slog_error_chain::ArrayErrorChain::new(self).serialize(
+                ::slog::KV::serialize(
+                    &#adapter::#constructor(self)#kv_options,
                     record,
                     serializer,
-                )
+                )?;
+                #emit_variant
+                ::std::result::Result::Ok(())
             }
         }
 
@@ -95,7 +295,8 @@ pub fn derive_slog_array_error(
                 key: ::slog::Key,
                 serializer: &mut dyn ::slog::Serializer,
             ) -> ::slog::Result {
-                ::slog_error_chain::ArrayErrorChain::new(self).serialize(
+                ::slog::Value::serialize(
+                    &#adapter::#new(self),
                     record,
                     key,
                     serializer,
@@ -103,14 +304,75 @@ pub fn derive_slog_array_error(
             }
         }
 
+        #serde_value
+    })
+}
+
+/// Returns `input` with a where clause requiring that it implement
+/// `std::error::Error`, on which all of the derived implementations rely (along
+/// with the [`field_bounds`] of its logged fields), and code reporting at its
+/// name a (non-generic) type that isn't an error.
+///
+/// For generic types, the bound makes the implementations apply whenever the
+/// type is an error (e.g., when a type parameter used as its source is an
+/// error). It's stated for all lifetimes `'__slog` so that, for other types,
+/// it isn't checked where it's declared: the assertion reports that instead,
+/// with a better message.
+fn with_bounds(input: &DeriveInput) -> syn::Result<(DeriveInput, TokenStream)> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    let bound: WherePredicate = parse_quote_spanned! {name.span()=>
+        for<'__slog> #name #ty_generics: ::slog_error_chain::__private::DeriveError
+    };
+    let generic = input.generics.type_params().next().is_some()
+        || input.generics.const_params().next().is_some();
+    let assertion = (!generic).then(|| {
+        quote_spanned! {name.span()=>
+            #[allow(dead_code)]
+            const _: () = {
+                fn assert_error<E>()
+                where
+                    E: ?::std::marker::Sized
+                        + ::slog_error_chain::__private::DeriveError,
+                {
+                }
+                fn assert #impl_generics() #where_clause {
+                    assert_error::<#name #ty_generics>();
+                }
+            };
+        }
+    });
+
+    let field_bounds = field_bounds(input)?;
+    let mut input = input.clone();
+    let predicates = &mut input.generics.make_where_clause().predicates;
+    predicates.push(bound);
+    predicates.extend(field_bounds);
+    Ok((input, assertion.unwrap_or_default()))
+}
+
+/// Returns implementations of `serde::Serialize` and `slog::SerdeValue` for
+/// `input` that delegate to `adapter` (constructed with `new`), where `owned`
+/// constructs its `to_sendable` value.
+#[cfg(feature = "nested-values")]
+fn serde_value(
+    input: &DeriveInput,
+    adapter: &TokenStream,
+    new: &TokenStream,
+    owned: TokenStream,
+) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    quote! {
         impl #impl_generics ::serde::Serialize for #name #ty_generics #where_clause {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
             where
                 S: serde::Serializer,
             {
-                ::slog_error_chain::ArrayErrorChain::new(self).serialize(
-                    serializer
-                )
+                ::serde::Serialize::serialize(&#adapter::#new(self), serializer)
             }
         }
 
@@ -120,7 +382,7 @@ pub fn derive_slog_array_error(
             }
 
             fn to_sendable(&self) -> Box<dyn ::slog::SerdeValue + Send + 'static> {
-                Box::new(::slog_error_chain::OwnedErrorChain::new(self))
+                Box::new(#owned)
             }
 
             fn serialize_fallback(
@@ -128,11 +390,12 @@ pub fn derive_slog_array_error(
                 key: ::slog::Key,
                 serializer: &mut dyn ::slog::Serializer,
             ) -> slog::Result<()> {
-                ::slog_error_chain::ArrayErrorChain::new(self)
-                    .serialize_fallback(key, serializer)
+                ::slog::SerdeValue::serialize_fallback(
+                    &#adapter::#new(self),
+                    key,
+                    serializer,
+                )
             }
         }
-    };
-
-    proc_macro::TokenStream::from(expanded)
+    }
 }
@@ -0,0 +1,126 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Generation of `slog_error_chain::ErrorTypeNames` implementations, which
+//! requires finding the source field of each variant the same way `thiserror`
+//! does.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Attribute;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Fields;
+use syn::Ident;
+use syn::Index;
+use syn::Member;
+
+/// Returns an implementation of `ErrorTypeNames` for `input`.
+pub(crate) fn impl_error_type_names(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let source = source_member(&data.fields, &input.attrs)
+                .map(|member| quote!(&self.#member));
+            push_type_names(is_transparent(&input.attrs), source)
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let transparent = is_transparent(&variant.attrs);
+                match source_member(&variant.fields, &variant.attrs) {
+                    Some(member) => {
+                        let push =
+                            push_type_names(transparent, Some(quote!(source)));
+                        quote! {
+                            Self::#ident { #member: source, .. } => { #push }
+                        }
+                    }
+                    None => {
+                        let push = push_type_names(transparent, None);
+                        quote!(Self::#ident { .. } => { #push })
+                    }
+                }
+            });
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        // `thiserror` doesn't support unions; record only the type itself.
+        Data::Union(_) => push_type_names(false, None),
+    };
+
+    quote! {
+        impl #impl_generics ::slog_error_chain::ErrorTypeNames for #name #ty_generics #where_clause {
+            fn type_names(&self, type_names: &mut ::std::vec::Vec<&'static str>) {
+                #[allow(unused_imports)]
+                use ::slog_error_chain::__private::PushTypeName as _;
+                #[allow(unused_imports)]
+                use ::slog_error_chain::__private::PushTypeNames as _;
+                #body
+            }
+        }
+    }
+}
+
+/// Returns code pushing the type name of `Self` (unless it is `transparent`)
+/// followed by those of `source`, an expression referring to its source
+/// field, if any.
+fn push_type_names(
+    transparent: bool,
+    source: Option<TokenStream>,
+) -> TokenStream {
+    let push_self = (!transparent)
+        .then(|| quote!(type_names.push(::std::any::type_name::<Self>());));
+    let push_source = source.map(|source| {
+        quote! {
+            (&::slog_error_chain::__private::Probe(#source))
+                .push_type_names(type_names);
+        }
+    });
+    quote!(#push_self #push_source)
+}
+
+/// Returns the member holding the source of an error with `fields`: the field
+/// marked `#[source]` or `#[from]`, or else the field named `source`. If the
+/// error is `#[error(transparent)]` (per `attrs`), this is its only field.
+pub(crate) fn source_member(
+    fields: &Fields,
+    attrs: &[Attribute],
+) -> Option<Member> {
+    if is_transparent(attrs) {
+        return members(fields).next();
+    }
+    let marked = fields.iter().zip(members(fields)).find(|(field, _)| {
+        field.attrs.iter().any(|attr| {
+            attr.path().is_ident("source") || attr.path().is_ident("from")
+        })
+    });
+    let named = || {
+        fields.iter().zip(members(fields)).find(|(field, _)| {
+            field.ident.as_ref().is_some_and(|ident| ident == "source")
+        })
+    };
+    marked.or_else(named).map(|(_, member)| member)
+}
+
+/// Returns the member used to access each of `fields`.
+pub(crate) fn members(fields: &Fields) -> impl Iterator<Item = Member> + '_ {
+    fields.iter().enumerate().map(|(i, field)| match &field.ident {
+        Some(ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(Index::from(i)),
+    })
+}
+
+/// Returns true if `attrs` includes `#[error(transparent)]`.
+pub(crate) fn is_transparent(attrs: &[Attribute]) -> bool {
+    attrs.iter().filter(|attr| attr.path().is_ident("error")).any(|attr| {
+        attr.parse_args::<Ident>().is_ok_and(|ident| ident == "transparent")
+    })
+}
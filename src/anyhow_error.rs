@@ -0,0 +1,343 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Conversions from `anyhow::Error`s into this crate's adapters.
+//!
+//! `anyhow::Error` doesn't implement `std::error::Error` itself, but it
+//! dereferences to one whose chain consists of its context layers (outermost
+//! first) followed by the wrapped error and its sources, which is what the
+//! adapters constructed here log.
+//!
+//! Since neither `anyhow::Error` nor `slog::Value` and `slog::KV` are defined
+//! here, an `anyhow::Error` can't be logged directly: [`InlineAnyhowChain`]
+//! and [`ArrayAnyhowChain`] wrap one (owned or borrowed) to log it.
+//!
+//! For the same reason, `ResultExt` isn't implemented for `anyhow::Result`;
+//! see its documentation.
+
+use crate::InlineErrorChain;
+use slog::Value;
+use slog::KV;
+use std::borrow::Borrow;
+use std::error::Error;
+use std::fmt;
+
+#[cfg(feature = "nested-values")]
+use crate::ArrayErrorChain;
+#[cfg(feature = "nested-values")]
+use crate::OwnedErrorChain;
+
+/// Returns `err` as the `std::error::Error` carrying its chain.
+fn as_dyn_error(err: &anyhow::Error) -> &(dyn Error + 'static) {
+    err.as_ref()
+}
+
+/// Logs an `anyhow::Error`, or a reference to one, as an [`InlineErrorChain`]
+/// would.
+///
+/// ```
+/// # use slog::{info, o};
+/// # use slog_error_chain::InlineAnyhowChain;
+/// # let log = slog::Logger::root(slog::Discard, o!());
+/// let err = anyhow::anyhow!("connection refused").context("starting server");
+/// info!(log, "failed"; "error" => InlineAnyhowChain(&err));
+/// let log = log.new(o!(InlineAnyhowChain(err)));
+/// ```
+#[derive(Debug)]
+pub struct InlineAnyhowChain<E = anyhow::Error>(pub E);
+
+impl<E: Borrow<anyhow::Error>> InlineAnyhowChain<E> {
+    fn chain(&self) -> InlineErrorChain<'_> {
+        InlineErrorChain::from(self.0.borrow())
+    }
+}
+
+impl<E: Borrow<anyhow::Error>> KV for InlineAnyhowChain<E> {
+    fn serialize(
+        &self,
+        record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        KV::serialize(&self.chain(), record, serializer)
+    }
+}
+
+impl<E: Borrow<anyhow::Error>> Value for InlineAnyhowChain<E> {
+    fn serialize(
+        &self,
+        record: &slog::Record,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        Value::serialize(&self.chain(), record, key, serializer)
+    }
+}
+
+impl<E: Borrow<anyhow::Error>> fmt::Display for InlineAnyhowChain<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chain().fmt(f)
+    }
+}
+
+/// Logs an `anyhow::Error`, or a reference to one, as an [`ArrayErrorChain`]
+/// would.
+#[cfg(feature = "nested-values")]
+#[derive(Debug)]
+pub struct ArrayAnyhowChain<E = anyhow::Error>(pub E);
+
+#[cfg(feature = "nested-values")]
+impl<E: Borrow<anyhow::Error>> ArrayAnyhowChain<E> {
+    fn chain(&self) -> ArrayErrorChain<'_> {
+        ArrayErrorChain::from(self.0.borrow())
+    }
+}
+
+#[cfg(feature = "nested-values")]
+impl<E: Borrow<anyhow::Error>> KV for ArrayAnyhowChain<E> {
+    fn serialize(
+        &self,
+        record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        KV::serialize(&self.chain(), record, serializer)
+    }
+}
+
+#[cfg(feature = "nested-values")]
+impl<E: Borrow<anyhow::Error>> Value for ArrayAnyhowChain<E> {
+    fn serialize(
+        &self,
+        record: &slog::Record,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        Value::serialize(&self.chain(), record, key, serializer)
+    }
+}
+
+#[cfg(feature = "nested-values")]
+impl<E: Borrow<anyhow::Error>> fmt::Display for ArrayAnyhowChain<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chain().fmt(f)
+    }
+}
+
+impl<'a> From<&'a anyhow::Error> for InlineErrorChain<'a> {
+    fn from(err: &'a anyhow::Error) -> Self {
+        Self::from_static(as_dyn_error(err))
+    }
+}
+
+#[cfg(feature = "nested-values")]
+impl<'a> From<&'a anyhow::Error> for ArrayErrorChain<'a> {
+    fn from(err: &'a anyhow::Error) -> Self {
+        Self::from_static(as_dyn_error(err))
+    }
+}
+
+#[cfg(feature = "nested-values")]
+impl From<&anyhow::Error> for OwnedErrorChain {
+    fn from(err: &anyhow::Error) -> Self {
+        Self::from_static(as_dyn_error(err))
+    }
+}
+
+#[cfg(feature = "nested-values")]
+impl From<anyhow::Error> for OwnedErrorChain {
+    fn from(err: anyhow::Error) -> Self {
+        Self::from(&err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::ErrorA;
+    use crate::tests::ErrorB;
+    use crate::tests::KeyValueSerializer;
+    use anyhow::Context;
+    use slog::{b, record, Level, KV};
+    use std::io;
+
+    fn err() -> anyhow::Error {
+        Err::<(), _>(ErrorB::B(ErrorA::A(io::Error::other("test error"))))
+            .context("loading config")
+            .context("starting server")
+            .unwrap_err()
+    }
+
+    #[test]
+    fn inline_anyhow_chain() {
+        let err = err();
+        assert_eq!(
+            InlineErrorChain::from(&err).to_string(),
+            "starting server: loading config: error b: error a: test error"
+        );
+
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let mut out = KeyValueSerializer::default();
+        KV::serialize(
+            &InlineErrorChain::from(&err).max_depth(3),
+            &dummy_record,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            out.0,
+            [(
+                "error".to_string(),
+                "starting server: loading config: error b: ... (2 more causes)"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn inline_anyhow_wrapper() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let expected =
+            "starting server: loading config: error b: error a: test error";
+
+        let err = err();
+        let mut out = KeyValueSerializer::default();
+        KV::serialize(&InlineAnyhowChain(&err), &dummy_record, &mut out)
+            .unwrap();
+        Value::serialize(
+            &InlineAnyhowChain(&err),
+            &dummy_record,
+            "err",
+            &mut out,
+        )
+        .unwrap();
+        KV::serialize(&InlineAnyhowChain(err), &dummy_record, &mut out)
+            .unwrap();
+        assert_eq!(
+            out.0,
+            [
+                ("error".to_string(), expected.to_string()),
+                ("err".to_string(), expected.to_string()),
+                ("error".to_string(), expected.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn anyhow_wrappers_in_slog_macros() {
+        // Both the owned and borrowed wrappers can be used with slog's macros.
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let err = err();
+        slog::info!(log, "failed"; "err" => InlineAnyhowChain(&err));
+        #[cfg(feature = "nested-values")]
+        slog::info!(log, "failed"; "err" => ArrayAnyhowChain(&err));
+        #[cfg(feature = "nested-values")]
+        let _log = log.new(slog::o!(ArrayAnyhowChain(anyhow::anyhow!("a"))));
+        let _log = log.new(slog::o!(InlineAnyhowChain(err)));
+    }
+
+    #[test]
+    fn anyhow_extension_traits() {
+        use crate::ErrorChainExt;
+        use crate::ResultExt;
+
+        let err = err();
+        assert_eq!(
+            err.inline_chain().to_string(),
+            InlineErrorChain::from(&err).to_string()
+        );
+
+        // `ResultExt` needs the `dyn Error` to be borrowed explicitly.
+        let log = slog::Logger::root(slog::Discard, slog::o!());
+        let result = Err::<(), _>(err);
+        let logged = result.as_ref().map_err(|err| &**err).ok_or_log(&log, "");
+        assert_eq!(logged, None);
+    }
+
+    #[cfg(feature = "nested-values")]
+    #[test]
+    fn array_anyhow_chain() {
+        let expected = serde_json::json!([
+            "starting server",
+            "loading config",
+            "error b",
+            "error a",
+            "test error",
+        ]);
+        let err = err();
+        assert_eq!(
+            serde_json::to_value(ArrayErrorChain::from(&err)).unwrap(),
+            expected
+        );
+        assert_eq!(
+            serde_json::to_value(OwnedErrorChain::from(&err)).unwrap(),
+            expected
+        );
+        assert_eq!(
+            serde_json::to_value(OwnedErrorChain::from(err)).unwrap(),
+            expected
+        );
+    }
+
+    #[cfg(feature = "nested-values")]
+    #[derive(Default)]
+    struct JsonSerializer(Vec<(String, serde_json::Value)>);
+
+    #[cfg(feature = "nested-values")]
+    impl slog::Serializer for JsonSerializer {
+        fn emit_arguments(
+            &mut self,
+            key: slog::Key,
+            val: &fmt::Arguments,
+        ) -> slog::Result {
+            self.0.push((key.to_string(), val.to_string().into()));
+            Ok(())
+        }
+
+        fn emit_serde(
+            &mut self,
+            key: slog::Key,
+            value: &dyn slog::SerdeValue,
+        ) -> slog::Result {
+            let value = serde_json::to_value(value.as_serde()).unwrap();
+            self.0.push((key.to_string(), value));
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "nested-values")]
+    #[test]
+    fn array_anyhow_wrapper() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let expected = serde_json::json!([
+            "starting server",
+            "loading config",
+            "error b",
+            "error a",
+            "test error",
+        ]);
+
+        let err = err();
+        let mut out = JsonSerializer::default();
+        KV::serialize(&ArrayAnyhowChain(&err), &dummy_record, &mut out)
+            .unwrap();
+        Value::serialize(
+            &ArrayAnyhowChain(&err),
+            &dummy_record,
+            "err",
+            &mut out,
+        )
+        .unwrap();
+        KV::serialize(&ArrayAnyhowChain(err), &dummy_record, &mut out).unwrap();
+        assert_eq!(
+            out.0,
+            [
+                ("error".to_string(), expected.clone()),
+                ("err".to_string(), expected.clone()),
+                ("error".to_string(), expected),
+            ]
+        );
+    }
+}
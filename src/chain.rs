@@ -0,0 +1,503 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`Chain`] walks the `source()` chain of an error, guarding against buggy
+//! `Error` implementations that form cycles and against arbitrarily deep
+//! chains. All of the adapters in this crate walk chains via `Chain`.
+
+use std::error::Error;
+use std::fmt;
+
+/// Default maximum number of layers (including the topmost error) walked by
+/// the adapters in this crate: as many as any walk examines, so that all but
+/// pathologically deep chains are printed in full.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = MAX_WALK_DEPTH;
+
+/// Maximum number of layers (including the topmost error) examined by walks
+/// that look beyond a chain's `max_depth`: to count the causes it omitted, or
+/// to find the root cause of a chain. This bounds the work done to log an
+/// arbitrarily deep (or endless) chain.
+pub(crate) const MAX_WALK_DEPTH: usize = 1024;
+
+/// Returns an iterator over `err` and each of its causes; see [`Chain`].
+///
+/// ```
+/// # use std::io;
+/// # #[derive(Debug, thiserror::Error)]
+/// # #[error("opening config")]
+/// # struct OpeningConfig(#[source] io::Error);
+/// let err = OpeningConfig(io::Error::from(io::ErrorKind::NotFound));
+///
+/// let chain = slog_error_chain::chain(&err);
+/// assert_eq!(chain.depth(), 2);
+///
+/// let not_found = slog_error_chain::chain(&err)
+///     .find_cause::<io::Error>()
+///     .is_some_and(|err| err.kind() == io::ErrorKind::NotFound);
+/// assert!(not_found);
+/// ```
+pub fn chain<'a, 'e>(err: &'a (dyn Error + 'e)) -> Chain<'a, dyn Error + 'e> {
+    Chain::new(err)
+}
+
+/// Describes why a [`Chain`] stopped before reaching the end of an error's
+/// `source()` chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Truncation {
+    /// The maximum depth was reached; this many causes were not walked.
+    Depth(usize),
+    /// The maximum depth was reached, and at least this many causes were not
+    /// walked; counting them stopped before the end of the chain.
+    DepthAtLeast(usize),
+    /// An error's `source()` returned an error already seen in this chain.
+    Cycle,
+}
+
+impl fmt::Display for Truncation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Truncation::Depth(1) => write!(f, "... (1 more cause)"),
+            Truncation::Depth(n) => write!(f, "... ({n} more causes)"),
+            Truncation::DepthAtLeast(n) => write!(f, "... ({n}+ more causes)"),
+            Truncation::Cycle => write!(f, "... (cycle detected)"),
+        }
+    }
+}
+
+/// Iterator over an error and each of its causes.
+///
+/// Iteration stops when an error's `source()` returns an error that has
+/// already been yielded, or after an optional maximum depth. Once exhausted,
+/// [`Chain::truncation()`] reports whether either of those happened.
+///
+/// Cycles are detected without allocating, in time proportional to the number
+/// of layers yielded.
+///
+/// `E` is the type of the yielded trait objects: `dyn Error + 'static` (the
+/// default) when walking a `'static` error, which allows downcasting each
+/// layer, or `dyn Error + 'e` otherwise.
+pub struct Chain<'a, E: ?Sized + 'a = dyn Error + 'static> {
+    root: &'a E,
+    next: Option<&'a E>,
+    // `next` as returned by `source()`, before its lifetime is shortened to
+    // that of `E`, and likewise the most recently yielded layer; these allow
+    // layers other than the root to be downcast.
+    next_static: Option<&'a (dyn Error + 'static)>,
+    last_static: Option<&'a (dyn Error + 'static)>,
+    depth: usize,
+    max_depth: usize,
+    // How far the chain is known to be free of cycles; see `extent()`.
+    extent: Extent,
+    cycle: bool,
+}
+
+// Derived `Clone`/`Copy` would require `E: Clone`.
+impl<E: ?Sized> Clone for Chain<'_, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E: ?Sized> Copy for Chain<'_, E> {}
+
+impl<'a, 'e> Chain<'a, dyn Error + 'e> {
+    /// Construct a `Chain` over `err` and each of its causes.
+    pub fn new(err: &'a (dyn Error + 'e)) -> Self {
+        Self::with_max_depth(err, usize::MAX)
+    }
+
+    /// Construct a `Chain` that walks at most `max_depth` layers of `err`. The
+    /// topmost error is always yielded, even if `max_depth` is 0.
+    pub(crate) fn with_max_depth(
+        err: &'a (dyn Error + 'e),
+        max_depth: usize,
+    ) -> Self {
+        Self {
+            root: err,
+            next: Some(err),
+            next_static: None,
+            last_static: None,
+            depth: 0,
+            max_depth: max_depth.max(1),
+            extent: Extent::AtLeast(1),
+            cycle: false,
+        }
+    }
+
+    /// Allow the topmost error to be downcast like its causes, given the same
+    /// error as a `'static` trait object (if its type is known to be
+    /// `'static`). This must be called before the chain is walked.
+    pub(crate) fn with_static_root(
+        mut self,
+        root: Option<&'a (dyn Error + 'static)>,
+    ) -> Self {
+        debug_assert_eq!(self.depth, 0);
+        self.next_static = root;
+        self
+    }
+
+    /// Walk at most `max_depth` layers (including the topmost error, which is
+    /// always yielded). By default, the entire chain is walked.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth.max(1);
+        self
+    }
+
+    /// Once the iterator is exhausted, returns `Some(_)` if the end of the
+    /// chain was not reached.
+    ///
+    /// If the maximum depth was reached, this walks the remainder of the chain
+    /// (still guarding against cycles) to count the omitted causes. At most
+    /// 1024 layers of the chain are counted; if it is deeper than that,
+    /// [`Truncation::DepthAtLeast`] is returned.
+    pub fn truncation(&self) -> Option<Truncation> {
+        if self.cycle {
+            return Some(Truncation::Cycle);
+        }
+        self.next?;
+        let limit = MAX_WALK_DEPTH.max(self.depth + 1);
+        let truncation = match extent(self.root, limit) {
+            Extent::Cycle(n) if n == self.depth => Truncation::Cycle,
+            // The chain could only be shorter than the layers already walked
+            // if `source()` isn't deterministic.
+            Extent::End(n) | Extent::Cycle(n) => {
+                Truncation::Depth(n.saturating_sub(self.depth))
+            }
+            Extent::AtLeast(n) => {
+                Truncation::DepthAtLeast(n.saturating_sub(self.depth))
+            }
+        };
+        Some(truncation)
+    }
+
+    /// Returns the deepest error walked by this chain: the root cause, unless
+    /// the chain was truncated.
+    pub fn root_cause(self) -> &'a (dyn Error + 'e) {
+        let root = self.root;
+        self.last().unwrap_or(root)
+    }
+
+    /// Returns the number of layers in the chain, including the topmost
+    /// error.
+    pub fn depth(self) -> usize {
+        self.count()
+    }
+
+    /// Returns the most recently yielded layer, if it can be downcast: that is,
+    /// if it is not the root of the chain (whose lifetime may be shorter than
+    /// `'static`), unless the root was given by `with_static_root()`.
+    pub(crate) fn last_static(&self) -> Option<&'a (dyn Error + 'static)> {
+        self.last_static
+    }
+
+    /// Returns true if the layer at `index` repeats an earlier layer.
+    ///
+    /// If `index` is beyond the extent of the chain checked so far, this
+    /// checks (at least) twice as far, up to `max_depth`, so that the cost of
+    /// checking is proportional to the number of layers yielded.
+    fn repeats(&mut self, index: usize) -> bool {
+        if let Extent::AtLeast(n) = self.extent {
+            if index >= n {
+                let limit = index.saturating_mul(2).max(8).min(self.max_depth);
+                self.extent = extent(self.root, limit);
+            }
+        }
+        matches!(self.extent, Extent::Cycle(n) if n == index)
+    }
+}
+
+/// How much of a chain is free of cycles, as determined by [`extent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Extent {
+    /// The chain ends after this many layers, all distinct.
+    End(usize),
+    /// The layer at this index repeats an earlier layer; those before it are
+    /// distinct.
+    Cycle(usize),
+    /// At least this many leading layers are distinct; the rest of the chain
+    /// wasn't examined.
+    AtLeast(usize),
+}
+
+/// Returns the extent of the chain starting at `root` that is free of cycles,
+/// examining at least `limit` layers (unless the chain is shorter).
+///
+/// This uses Brent's cycle detection algorithm, which takes constant space. If
+/// the first layer that repeats an earlier one is at index `n`, it is found
+/// within `3n` steps, so if none is found within `3 * limit` steps, the first
+/// `limit` layers are distinct. Finding the index of the first repeated layer
+/// then takes another `n` steps.
+///
+/// Errors are compared by both address and vtable, since a source stored at
+/// the start of its parent shares its parent's address. Vtables are not
+/// guaranteed to be unique, so a cycle may occasionally be detected one
+/// repetition late, but it will still be detected.
+fn extent<'e>(root: &(dyn Error + 'e), limit: usize) -> Extent {
+    fn source<'a, 'e>(
+        err: &'a (dyn Error + 'e),
+    ) -> Option<&'a (dyn Error + 'e)> {
+        err.source().map(|s| s as &(dyn Error + 'e))
+    }
+
+    let max_steps = limit.saturating_mul(3).saturating_add(2);
+    let mut tortoise = root;
+    let Some(mut hare) = source(root) else {
+        return Extent::End(1);
+    };
+    // `hare` is the layer at index `steps`; `tortoise` jumps to it every
+    // `power` steps, and `len` is the number of steps since it last did.
+    let mut steps = 1;
+    let mut power = 1;
+    let mut len = 1;
+    while !std::ptr::eq(tortoise, hare) {
+        if steps >= max_steps {
+            return Extent::AtLeast(limit);
+        }
+        if power == len {
+            tortoise = hare;
+            power *= 2;
+            len = 0;
+        }
+        let Some(next) = source(hare) else {
+            return Extent::End(steps + 1);
+        };
+        hare = next;
+        steps += 1;
+        len += 1;
+    }
+
+    // The cycle is `len` layers long; the first repeated layer is the first
+    // that is the same as the layer `len` layers after it.
+    let mut first = root;
+    let mut ahead = root;
+    for _ in 0..len {
+        let Some(next) = source(ahead) else {
+            return Extent::AtLeast(limit);
+        };
+        ahead = next;
+    }
+    let mut start = 0;
+    while !std::ptr::eq(first, ahead) {
+        let (Some(a), Some(b)) = (source(first), source(ahead)) else {
+            return Extent::AtLeast(limit);
+        };
+        first = a;
+        ahead = b;
+        start += 1;
+    }
+    Extent::Cycle(start + len)
+}
+
+/// Returns the deepest layer of `err`'s chain and the number of layers in the
+/// chain, walking at most 1024 layers.
+pub(crate) fn root_and_depth<'a, 'e>(
+    err: &'a (dyn Error + 'e),
+) -> (&'a (dyn Error + 'e), usize) {
+    Chain::with_max_depth(err, MAX_WALK_DEPTH)
+        .fold((err, 0), |(_, depth), layer| (layer, depth + 1))
+}
+
+impl<'a> Chain<'a> {
+    /// Returns the first layer of the chain (starting with the topmost error)
+    /// that is a `T`.
+    ///
+    /// This is a convenience for the common case of
+    /// `chain.find_map(|err| err.downcast_ref::<T>())`.
+    pub fn find_cause<T: Error + 'static>(mut self) -> Option<&'a T> {
+        self.find_map(|err| err.downcast_ref::<T>())
+    }
+}
+
+impl<'a, 'e> Iterator for Chain<'a, dyn Error + 'e> {
+    type Item = &'a (dyn Error + 'e);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let err = self.next?;
+        if self.depth >= self.max_depth {
+            return None;
+        }
+        if self.repeats(self.depth) {
+            self.cycle = true;
+            self.next = None;
+            return None;
+        }
+        self.depth += 1;
+        self.last_static = self.next_static;
+        self.next_static = err.source();
+        self.next = self.next_static.map(|s| s as &(dyn Error + 'e));
+        Some(err)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An error whose `source()` is `inner`, or itself if `inner` is `None`.
+    #[derive(Debug)]
+    pub(crate) struct CyclicError {
+        pub(crate) inner: Option<Box<CyclicError>>,
+    }
+
+    impl fmt::Display for CyclicError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "cyclic")
+        }
+    }
+
+    impl Error for CyclicError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            match &self.inner {
+                Some(inner) => Some(&**inner),
+                None => Some(self),
+            }
+        }
+    }
+
+    /// An error with `depth - 1` causes.
+    #[derive(Debug)]
+    pub(crate) struct DeepError(pub(crate) usize);
+
+    impl fmt::Display for DeepError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "depth {}", self.0)
+        }
+    }
+
+    impl Error for DeepError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            // Leaking is fine for tests; it gives us a `'static` source.
+            (self.0 > 1).then(|| {
+                &*Box::leak(Box::new(DeepError(self.0 - 1))) as &dyn Error
+            })
+        }
+    }
+
+    #[test]
+    fn chain_detects_cycles() {
+        let err = CyclicError { inner: None };
+        let mut chain = Chain::with_max_depth(&err, DEFAULT_MAX_DEPTH);
+        assert_eq!(chain.by_ref().count(), 1);
+        assert_eq!(chain.truncation(), Some(Truncation::Cycle));
+
+        let err = CyclicError {
+            inner: Some(Box::new(CyclicError {
+                inner: Some(Box::new(CyclicError { inner: None })),
+            })),
+        };
+        let mut chain = Chain::with_max_depth(&err, DEFAULT_MAX_DEPTH);
+        assert_eq!(chain.by_ref().count(), 3);
+        assert_eq!(chain.truncation(), Some(Truncation::Cycle));
+
+        // Hitting the depth limit before the cycle still reports the number
+        // of (distinct) remaining causes.
+        let mut chain = Chain::with_max_depth(&err, 1);
+        assert_eq!(chain.by_ref().count(), 1);
+        assert_eq!(chain.truncation(), Some(Truncation::Depth(2)));
+    }
+
+    #[test]
+    fn chain_bounds_deep_chains() {
+        // Chains much deeper than the limit, or endless ones, are only walked
+        // as far as `MAX_WALK_DEPTH` to count the omitted causes.
+        for err in [DeepError(20_000), DeepError(usize::MAX)] {
+            let mut chain = Chain::with_max_depth(&err, 32);
+            assert_eq!(chain.by_ref().count(), 32);
+            assert_eq!(
+                chain.truncation(),
+                Some(Truncation::DepthAtLeast(MAX_WALK_DEPTH - 32))
+            );
+            let mut chain = Chain::with_max_depth(&err, DEFAULT_MAX_DEPTH);
+            assert_eq!(chain.by_ref().count(), MAX_WALK_DEPTH);
+            assert_eq!(chain.truncation(), Some(Truncation::DepthAtLeast(1)));
+            let (root, depth) = root_and_depth(&err);
+            assert_eq!(depth, MAX_WALK_DEPTH);
+            assert_eq!(root.to_string(), format!("depth {}", err.0 - 1023));
+        }
+
+        let err = DeepError(MAX_WALK_DEPTH);
+        let mut chain = Chain::with_max_depth(&err, 2);
+        assert_eq!(chain.by_ref().count(), 2);
+        assert_eq!(
+            chain.truncation(),
+            Some(Truncation::Depth(MAX_WALK_DEPTH - 2))
+        );
+        assert_eq!(root_and_depth(&err).0.to_string(), "depth 1");
+
+        // A cycle following many distinct layers ends the chain at its first
+        // repetition.
+        let mut err = CyclicError { inner: None };
+        for _ in 0..99 {
+            err = CyclicError { inner: Some(Box::new(err)) };
+        }
+        let mut layers = super::chain(&err);
+        assert_eq!(layers.by_ref().count(), 100);
+        assert_eq!(layers.truncation(), Some(Truncation::Cycle));
+        let mut chain = Chain::with_max_depth(&err, 40);
+        assert_eq!(chain.by_ref().count(), 40);
+        assert_eq!(chain.truncation(), Some(Truncation::Depth(60)));
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("outer")]
+    struct Outer(#[source] Middle);
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("middle")]
+    struct Middle(#[source] std::io::Error);
+
+    #[test]
+    fn chain_helpers() {
+        let err =
+            Outer(Middle(std::io::Error::from(std::io::ErrorKind::NotFound)));
+
+        let messages = chain(&err).map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, ["outer", "middle", "entity not found"]);
+        assert_eq!(chain(&err).depth(), 3);
+        assert_eq!(chain(&err).max_depth(2).depth(), 2);
+
+        let root = chain(&err).root_cause();
+        assert_eq!(root.to_string(), "entity not found");
+        let root = root.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(root.kind(), std::io::ErrorKind::NotFound);
+
+        assert!(chain(&err).find_cause::<Outer>().is_some());
+        assert_eq!(
+            chain(&err).find_cause::<Middle>().unwrap().to_string(),
+            "middle"
+        );
+        assert!(chain(&err.0 .0).find_cause::<Middle>().is_none());
+        assert_eq!(
+            chain(&err).find_map(|e| e
+                .downcast_ref::<std::io::Error>()
+                .map(|e| e.kind())),
+            Some(std::io::ErrorKind::NotFound)
+        );
+
+        // A single error is its own root cause.
+        let err = Middle(std::io::Error::other("inner"));
+        assert_eq!(chain(&err.0).root_cause().to_string(), "inner");
+        assert_eq!(chain(&err.0).depth(), 1);
+    }
+
+    #[test]
+    fn chain_limits_depth() {
+        let err = DeepError(5);
+        let mut chain = Chain::with_max_depth(&err, DEFAULT_MAX_DEPTH);
+        assert_eq!(chain.by_ref().count(), 5);
+        assert_eq!(chain.truncation(), None);
+
+        let mut chain = Chain::with_max_depth(&err, 5);
+        assert_eq!(chain.by_ref().count(), 5);
+        assert_eq!(chain.truncation(), None);
+
+        let mut chain = Chain::with_max_depth(&err, 3);
+        assert_eq!(chain.by_ref().count(), 3);
+        assert_eq!(chain.truncation(), Some(Truncation::Depth(2)));
+
+        let mut chain = Chain::with_max_depth(&err, 0);
+        assert_eq!(chain.by_ref().count(), 1);
+        assert_eq!(chain.truncation(), Some(Truncation::Depth(4)));
+    }
+}
@@ -0,0 +1,231 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Extension traits providing this crate's adapters as methods.
+
+use crate::InlineErrorChain;
+use crate::ReportErrorChain;
+use std::error::Error;
+use std::fmt;
+
+#[cfg(feature = "nested-values")]
+use crate::ArrayErrorChain;
+#[cfg(feature = "nested-values")]
+use crate::OwnedErrorChain;
+
+/// Provides this crate's adapters as methods on every error type (including
+/// `dyn Error`, and so boxed errors), without needing to import the adapter
+/// types or derive anything on the error:
+///
+/// ```
+/// use slog_error_chain::ErrorChainExt;
+/// # let log = slog::Logger::root(slog::Discard, slog::o!());
+/// # let err: Box<dyn std::error::Error> = std::io::Error::other("oops").into();
+///
+/// slog::error!(log, "request failed"; "err" => err.inline_chain());
+/// eprintln!("{}", err.report());
+/// ```
+///
+/// The methods are also found on an `anyhow::Error` (e.g.,
+/// `err.inline_chain()`), through the `dyn Error` it dereferences to.
+pub trait ErrorChainExt {
+    /// Returns an [`InlineErrorChain`] for `self`.
+    fn inline_chain(&self) -> InlineErrorChain<'_>;
+
+    /// Returns an [`ArrayErrorChain`] for `self`.
+    #[cfg(feature = "nested-values")]
+    fn array_chain(&self) -> ArrayErrorChain<'_>;
+
+    /// Returns an [`OwnedErrorChain`] capturing the chain of `self`.
+    #[cfg(feature = "nested-values")]
+    fn owned_chain(&self) -> OwnedErrorChain;
+
+    /// Returns a [`ReportErrorChain`] for `self`.
+    fn report(&self) -> ReportErrorChain<'_>;
+}
+
+macro_rules! impl_error_chain_ext {
+    ($([$($generics:tt)*] $ty:ty),* $(,)?) => {
+        $(
+            impl<$($generics)*> ErrorChainExt for $ty {
+                fn inline_chain(&self) -> InlineErrorChain<'_> {
+                    InlineErrorChain::new(self)
+                }
+
+                #[cfg(feature = "nested-values")]
+                fn array_chain(&self) -> ArrayErrorChain<'_> {
+                    ArrayErrorChain::new(self)
+                }
+
+                #[cfg(feature = "nested-values")]
+                fn owned_chain(&self) -> OwnedErrorChain {
+                    OwnedErrorChain::new(self)
+                }
+
+                fn report(&self) -> ReportErrorChain<'_> {
+                    ReportErrorChain::new(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_error_chain_ext! {
+    [E: Error] E,
+    ['a] dyn Error + 'a,
+    ['a] dyn Error + Send + 'a,
+    ['a] dyn Error + Send + Sync + 'a,
+}
+
+/// Logs the error chains of failed `Result`s, for operations whose errors are
+/// logged and then passed on or ignored:
+///
+/// ```
+/// use slog_error_chain::ResultExt;
+/// # let log = slog::Logger::root(slog::Discard, slog::o!());
+///
+/// let removed = std::fs::remove_file("/tmp/stale.lock")
+///     .ok_or_log(&log, "failed to remove stale lock file")
+///     .is_some();
+/// ```
+///
+/// The chain is logged via [`InlineErrorChain`]'s `slog::KV` implementation
+/// (i.e., under the key `error`). Note that the records' locations (e.g., the
+/// module used by filters such as `slog-envlogger`) are in this crate, rather
+/// than at the caller; use [`log_chain!`](crate::log_chain) et al. where that
+/// matters.
+///
+/// `ResultExt` is only implemented for `Result`s whose error type implements
+/// [`Error`]. That excludes `anyhow::Result`: `anyhow::Error` only
+/// dereferences to a `dyn Error`, and can't be given an implementation of its
+/// own, as `anyhow` might implement `Error` for it in the future. Borrow that
+/// `dyn Error` explicitly instead, e.g.,
+/// `result.as_ref().map_err(|err| &**err).ok_or_log(&log, "...")`.
+pub trait ResultExt<T, E> {
+    /// If `self` is an error, log `msg` and its chain at error level. Returns
+    /// `self`.
+    fn log_err(self, log: &slog::Logger, msg: &str) -> Self;
+
+    /// If `self` is an error, log `msg` and its chain at warning level.
+    /// Returns `self`.
+    fn log_warn(self, log: &slog::Logger, msg: &str) -> Self;
+
+    /// If `self` is an error, log the message returned by `msg` (given the
+    /// error) and its chain at error level. Returns `self`.
+    fn log_err_with<F, M>(self, log: &slog::Logger, msg: F) -> Self
+    where
+        F: FnOnce(&E) -> M,
+        M: fmt::Display;
+
+    /// If `self` is an error, log `msg` and its chain at error level. Returns
+    /// the success value of `self`, if any.
+    fn ok_or_log(self, log: &slog::Logger, msg: &str) -> Option<T>;
+}
+
+impl<T, E: Error> ResultExt<T, E> for Result<T, E> {
+    fn log_err(self, log: &slog::Logger, msg: &str) -> Self {
+        self.log_err_with(log, |_| msg)
+    }
+
+    fn log_warn(self, log: &slog::Logger, msg: &str) -> Self {
+        if let Err(err) = &self {
+            slog::warn!(log, "{}", msg; InlineErrorChain::new(err));
+        }
+        self
+    }
+
+    fn log_err_with<F, M>(self, log: &slog::Logger, msg: F) -> Self
+    where
+        F: FnOnce(&E) -> M,
+        M: fmt::Display,
+    {
+        if let Err(err) = &self {
+            slog::error!(log, "{}", msg(err); InlineErrorChain::new(err));
+        }
+        self
+    }
+
+    fn ok_or_log(self, log: &slog::Logger, msg: &str) -> Option<T> {
+        self.log_err(log, msg).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::tests::Logged;
+    use crate::level::tests::Records;
+    use crate::tests::ErrorA;
+    use crate::tests::ErrorB;
+    use slog::Level;
+    use std::io;
+
+    #[test]
+    fn result_ext() {
+        let records = Records::default();
+        let log = records.logger();
+        let err = || ErrorA::A(io::Error::other("test error"));
+
+        assert_eq!(Ok::<_, ErrorA>(1).log_err(&log, "unused").unwrap(), 1);
+        assert_eq!(Ok::<_, ErrorA>(1).ok_or_log(&log, "unused"), Some(1));
+        assert!(records.take().is_empty());
+
+        assert!(Err::<(), _>(err()).log_err(&log, "failed").is_err());
+        assert!(Err::<(), _>(err()).log_warn(&log, "failed").is_err());
+        let result = Err::<(), _>(err())
+            .log_err_with(&log, |err| format!("failed: {}", err));
+        assert!(result.is_err());
+        assert_eq!(Err::<(), _>(err()).ok_or_log(&log, "ignored"), None);
+
+        let chain = "error=error a: test error";
+        let logged = |level, msg: &str| -> Logged {
+            (level, msg.to_string(), vec![chain.to_string()])
+        };
+        assert_eq!(
+            records.take(),
+            [
+                logged(Level::Error, "failed"),
+                logged(Level::Warning, "failed"),
+                logged(Level::Error, "failed: error a"),
+                logged(Level::Error, "ignored"),
+            ]
+        );
+    }
+
+    #[test]
+    fn error_chain_ext() {
+        let err = ErrorB::B(ErrorA::A(io::Error::other("test error")));
+        assert_eq!(
+            err.inline_chain().to_string(),
+            "error b: error a: test error"
+        );
+        assert_eq!(
+            err.report().to_string(),
+            ReportErrorChain::new(&err).to_string()
+        );
+
+        let boxed: Box<dyn Error> = Box::new(err);
+        assert_eq!(
+            boxed.inline_chain().max_depth(2).to_string(),
+            "error b: error a: ... (1 more cause)"
+        );
+
+        let boxed: Box<dyn Error + Send + Sync> =
+            ErrorA::A(io::Error::other("test error")).into();
+        assert_eq!(boxed.inline_chain().to_string(), "error a: test error");
+
+        #[cfg(feature = "nested-values")]
+        {
+            let expected = serde_json::json!(["error a", "test error"]);
+            assert_eq!(
+                serde_json::to_value(boxed.array_chain()).unwrap(),
+                expected
+            );
+            assert_eq!(
+                serde_json::to_value(boxed.owned_chain()).unwrap(),
+                expected
+            );
+        }
+    }
+}
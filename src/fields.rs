@@ -0,0 +1,301 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`ErrorFields`] lets the layers of an error chain log structured data
+//! (e.g., the path of a file that couldn't be opened) alongside their
+//! messages.
+
+use crate::chain::Chain;
+use crate::keys;
+use std::any::TypeId;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::RwLock;
+
+/// Logs additional key/value pairs describing an error.
+///
+/// When an error chain is logged via the `slog::KV` implementation of one of
+/// this crate's adapters, the fields of each layer that implements
+/// `ErrorFields` are logged under `{key}.{index}.`, where `index` is the
+/// position of the layer in the chain: e.g., a `path` field of the topmost
+/// error is logged as `error.0.path`.
+///
+/// The causes of an error are only known as `&dyn Error`s, so their types must
+/// be registered with [`register_error_fields`] for their fields to be found.
+/// The topmost error may not be `'static`, and so can't be looked up the same
+/// way: its fields are only logged if the adapter is constructed with
+/// `with_fields` (e.g., [`InlineErrorChain::with_fields`]), or if its type is
+/// registered and the adapter is constructed from a `'static` error (by
+/// `from_static`, e.g.,
+/// [`FlatErrorChain::from_static`](crate::FlatErrorChain::from_static), or by
+/// `ObjectErrorChain::new`).
+///
+/// Owned chains (e.g., `OwnedErrorChain`) don't capture fields.
+///
+/// [`InlineErrorChain::with_fields`]: crate::InlineErrorChain::with_fields
+pub trait ErrorFields {
+    /// Serialize the fields of `self` via `serializer`, with keys relative to
+    /// this error (e.g., `path` rather than `error.0.path`).
+    fn serialize_fields(
+        &self,
+        record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result;
+}
+
+type Lookup =
+    for<'a> fn(&'a (dyn Error + 'static)) -> Option<&'a dyn ErrorFields>;
+
+static REGISTRY: RwLock<Vec<(TypeId, Lookup)>> = RwLock::new(Vec::new());
+
+/// Set once any type has been registered, so that chains needn't be walked
+/// (nor the registry locked) to look for fields until then.
+static REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// Register `E` as implementing [`ErrorFields`], so that the fields of errors
+/// of type `E` are logged when they appear as causes in an error chain.
+///
+/// Registering a type more than once has no further effect.
+pub fn register_error_fields<E>()
+where
+    E: Error + ErrorFields + 'static,
+{
+    fn lookup<'a, E: Error + ErrorFields + 'static>(
+        err: &'a (dyn Error + 'static),
+    ) -> Option<&'a dyn ErrorFields> {
+        err.downcast_ref::<E>().map(|err| err as &dyn ErrorFields)
+    }
+
+    let type_id = TypeId::of::<E>();
+    let mut registry = REGISTRY.write().unwrap_or_else(|err| err.into_inner());
+    if !registry.iter().any(|(id, _)| *id == type_id) {
+        registry.push((type_id, lookup::<E>));
+    }
+    REGISTERED.store(true, Ordering::Release);
+}
+
+/// Returns the [`ErrorFields`] implementation of `err`, if its type has been
+/// registered.
+pub(crate) fn fields_of<'a>(
+    err: &'a (dyn Error + 'static),
+) -> Option<&'a dyn ErrorFields> {
+    if !REGISTERED.load(Ordering::Acquire) {
+        return None;
+    }
+    let registry = REGISTRY.read().unwrap_or_else(|err| err.into_inner());
+    registry.iter().find_map(|(_, lookup)| lookup(err))
+}
+
+/// Emit the fields of each of the (at most `max_depth`) layers of `err` under
+/// `{key}.{index}.`. `top` provides the fields of `err` itself, which can't be
+/// looked up in the registry.
+///
+/// Unless a type has been registered, only `top` is consulted, and the chain
+/// isn't walked.
+pub(crate) fn emit_chain_fields(
+    key: &str,
+    err: &dyn Error,
+    top: Option<&dyn ErrorFields>,
+    max_depth: usize,
+    record: &slog::Record,
+    serializer: &mut dyn slog::Serializer,
+) -> slog::Result {
+    if let Some(fields) = top {
+        emit_layer_fields(key, 0, fields, record, serializer)?;
+    }
+    if !REGISTERED.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    let mut chain = Chain::with_max_depth(err, max_depth);
+    chain.next();
+    let mut index = 1;
+    while chain.next().is_some() {
+        if let Some(fields) = chain.last_static().and_then(fields_of) {
+            emit_layer_fields(key, index, fields, record, serializer)?;
+        }
+        index += 1;
+    }
+    Ok(())
+}
+
+/// Emit the fields of the layer at `index` under `{key}.{index}.`.
+fn emit_layer_fields(
+    key: &str,
+    index: usize,
+    fields: &dyn ErrorFields,
+    record: &slog::Record,
+    serializer: &mut dyn slog::Serializer,
+) -> slog::Result {
+    let mut prefixed = Prefixed { key, index, serializer };
+    fields.serialize_fields(record, &mut prefixed)
+}
+
+/// Serializer that forwards each key/value pair to `serializer`, prepending
+/// `{key}.{index}.` to its key.
+struct Prefixed<'a> {
+    key: &'a str,
+    index: usize,
+    serializer: &'a mut dyn slog::Serializer,
+}
+
+impl Prefixed<'_> {
+    // Without slog's `dynamic-keys` feature, `slog::Key` is already a `&str`.
+    #[allow(clippy::useless_asref)]
+    fn key(&self, key: slog::Key) -> slog::Key {
+        keys::field(self.key, self.index, key.as_ref())
+    }
+}
+
+macro_rules! forward {
+    ($($emit:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $emit(&mut self, key: slog::Key, val: $ty) -> slog::Result {
+                let key = self.key(key);
+                self.serializer.$emit(key, val)
+            }
+        )*
+    };
+}
+
+impl slog::Serializer for Prefixed<'_> {
+    forward!(
+        emit_usize(usize),
+        emit_isize(isize),
+        emit_bool(bool),
+        emit_char(char),
+        emit_u8(u8),
+        emit_i8(i8),
+        emit_u16(u16),
+        emit_i16(i16),
+        emit_u32(u32),
+        emit_i32(i32),
+        emit_f32(f32),
+        emit_u64(u64),
+        emit_i64(i64),
+        emit_f64(f64),
+        emit_u128(u128),
+        emit_i128(i128),
+        emit_str(&str),
+        emit_arguments(&fmt::Arguments<'_>),
+    );
+
+    fn emit_unit(&mut self, key: slog::Key) -> slog::Result {
+        let key = self.key(key);
+        self.serializer.emit_unit(key)
+    }
+
+    fn emit_none(&mut self, key: slog::Key) -> slog::Result {
+        let key = self.key(key);
+        self.serializer.emit_none(key)
+    }
+
+    fn emit_bytes(
+        &mut self,
+        key: slog::Key,
+        bytes: &[u8],
+        kind: slog::BytesKind,
+    ) -> slog::Result {
+        let key = self.key(key);
+        self.serializer.emit_bytes(key, bytes, kind)
+    }
+
+    #[cfg(feature = "nested-values")]
+    fn emit_serde(
+        &mut self,
+        key: slog::Key,
+        value: &dyn slog::SerdeValue,
+    ) -> slog::Result {
+        let key = self.key(key);
+        self.serializer.emit_serde(key, value)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tests::KeyValueSerializer;
+    use slog::{b, record, Level};
+    use std::io;
+
+    #[derive(Debug)]
+    pub(crate) struct OpeningFile {
+        pub(crate) path: &'static str,
+        pub(crate) err: io::Error,
+    }
+
+    impl fmt::Display for OpeningFile {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "opening {}", self.path)
+        }
+    }
+
+    impl Error for OpeningFile {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.err)
+        }
+    }
+
+    impl ErrorFields for OpeningFile {
+        fn serialize_fields(
+            &self,
+            _record: &slog::Record,
+            serializer: &mut dyn slog::Serializer,
+        ) -> slog::Result {
+            serializer.emit_str("path", self.path)?;
+            serializer.emit_usize("len", self.path.len())
+        }
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct Loading(pub(crate) OpeningFile);
+
+    impl fmt::Display for Loading {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("loading config")
+        }
+    }
+
+    impl Error for Loading {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    fn emit(err: &dyn Error, top: Option<&dyn ErrorFields>) -> Vec<String> {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let mut out = KeyValueSerializer::default();
+        emit_chain_fields("err", err, top, 32, &dummy_record, &mut out)
+            .unwrap();
+        out.0.into_iter().map(|(k, v)| format!("{k}={v}")).collect()
+    }
+
+    #[test]
+    fn chain_fields() {
+        let err = Loading(OpeningFile {
+            path: "/etc/config",
+            err: io::Error::other("not found"),
+        });
+
+        // The topmost error's fields are only known if given explicitly.
+        assert_eq!(emit(&err.0, None), Vec::<String>::new());
+        assert_eq!(
+            emit(&err.0, Some(&err.0)),
+            ["err.0.path=/etc/config", "err.0.len=11"]
+        );
+
+        register_error_fields::<OpeningFile>();
+        register_error_fields::<OpeningFile>();
+        let registry = REGISTRY.read().unwrap();
+        let type_id = TypeId::of::<OpeningFile>();
+        assert_eq!(registry.iter().filter(|(id, _)| *id == type_id).count(), 1);
+        drop(registry);
+        assert_eq!(
+            emit(&err, None),
+            ["err.1.path=/etc/config", "err.1.len=11"]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`FlatErrorChain`] logs each cause of an error as its own key, for loggers
+//! that don't support nested values (e.g., logfmt or journald drains).
+
+use crate::chain::Chain;
+use crate::fields;
+use crate::fields::ErrorFields;
+use crate::format::chain_format_options;
+use crate::format::ChainFormat;
+use crate::InlineErrorChain;
+use slog::Value;
+use slog::KV;
+use std::error::Error;
+use std::fmt;
+
+/// Adapter for [`Error`]s that logs each error in the chain as a separate
+/// key: `error.0` for the topmost error, `error.1` for its source, and so on.
+///
+/// This gives each cause its own field without requiring the `nested-values`
+/// feature or a drain that supports it. When logged with an explicit key
+/// (e.g., `"my-err" => FlatErrorChain::new(&err)`), that key is used as the
+/// prefix instead of `error`.
+///
+/// If the chain is truncated (see [`FlatErrorChain::max_depth`]), a final key
+/// describing the truncation is logged after the last cause.
+///
+/// For each cause that is an [`std::io::Error`] (other than a bare custom
+/// error), its kind, OS error code, and errno name are also logged as
+/// `error.N.kind`, `error.N.raw_os_error`, and `error.N.errno`, where known.
+/// The same goes for the topmost error if the chain was constructed by
+/// [`FlatErrorChain::from_static`].
+///
+/// `FlatErrorChain`'s `Display` implementation matches that of
+/// [`InlineErrorChain`].
+pub struct FlatErrorChain<'a> {
+    err: &'a dyn Error,
+    // `err`, if its type is known to be `'static`; see `from_static()`.
+    err_static: Option<&'a (dyn Error + 'static)>,
+    fields: Option<&'a dyn ErrorFields>,
+    format: ChainFormat,
+}
+
+impl<'a> FlatErrorChain<'a> {
+    /// Construct a new `FlatErrorChain` from an error.
+    pub fn new(err: &'a dyn Error) -> Self {
+        let format = ChainFormat::default();
+        Self { err, err_static: None, fields: None, format }
+    }
+
+    /// Construct a new `FlatErrorChain` from a `'static` error, which (unlike
+    /// an error of unknown lifetime) can be downcast: if it is an
+    /// [`std::io::Error`], its details are logged like those of its causes,
+    /// and if its type has been registered with
+    /// [`register_error_fields`](crate::register_error_fields), its
+    /// [`ErrorFields`] are also logged.
+    pub fn from_static(err: &'a (dyn Error + 'static)) -> Self {
+        let fields = fields::fields_of(err);
+        Self { err_static: Some(err), fields, ..Self::new(err) }
+    }
+
+    /// Construct a new `FlatErrorChain` from an error whose own
+    /// [`ErrorFields`] are also logged, as `{key}.0.{field}`. See
+    /// [`InlineErrorChain::with_fields`].
+    pub fn with_fields<E: Error + ErrorFields>(err: &'a E) -> Self {
+        Self { fields: Some(err), ..Self::new(err) }
+    }
+
+    chain_format_options! {
+        ///
+        /// This is also the prefix of each key logged for the chain.
+        with_key,
+        emit_root_cause,
+        ///
+        /// The marker is logged under a final key.
+        max_depth,
+        suppress_double_speak,
+        max_layer_len,
+        ///
+        /// This limits the total length of all logged messages; no further keys
+        /// are logged once it has been reached.
+        max_len,
+        escape,
+        errno_names,
+    }
+
+    fn emit(
+        &self,
+        key: &str,
+        record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        let format = self.format.for_slog();
+        let layers = Chain::with_max_depth(self.err, format.max_depth)
+            .with_static_root(self.err_static);
+        format.emit_indexed_layers(key, layers, serializer)?;
+        fields::emit_chain_fields(
+            key,
+            self.err,
+            self.fields,
+            format.max_depth,
+            record,
+            serializer,
+        )
+    }
+}
+
+impl KV for FlatErrorChain<'_> {
+    fn serialize(
+        &self,
+        record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        self.emit(self.format.key, record, serializer)?;
+        self.format.for_slog().emit_root_cause_of(self.err, serializer)
+    }
+}
+
+impl Value for FlatErrorChain<'_> {
+    // Without slog's `dynamic-keys` feature, `slog::Key` is already a `&str`.
+    #[allow(clippy::useless_asref)]
+    fn serialize(
+        &self,
+        record: &slog::Record,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        self.emit(key.as_ref(), record, serializer)
+    }
+}
+
+impl fmt::Display for FlatErrorChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        InlineErrorChain::with_format(self.err, self.err_static, self.format)
+            .fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::tests::DeepError;
+    use crate::tests::{EmbeddingError, ErrorA, ErrorB, KeyValueSerializer};
+    use slog::{b, record, Level};
+    use std::io;
+
+    fn serialize_kv(kv: &dyn KV) -> Vec<(String, String)> {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let mut out = KeyValueSerializer::default();
+        kv.serialize(&dummy_record, &mut out).unwrap();
+        out.0
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn flat_error_chain_formatting() {
+        let err = ErrorB::B(ErrorA::A(io::Error::other("test\nerror")));
+        let chain = FlatErrorChain::new(&err);
+        assert_eq!(chain.to_string(), "error b: error a: test\nerror");
+        assert_eq!(
+            serialize_kv(&chain),
+            pairs(&[
+                ("error.0", "error b"),
+                ("error.1", "error a"),
+                ("error.2", r"test\nerror"),
+            ])
+        );
+
+        assert_eq!(
+            serialize_kv(
+                &FlatErrorChain::new(&err).with_key("err").escape(false)
+            ),
+            pairs(&[
+                ("err.0", "error b"),
+                ("err.1", "error a"),
+                ("err.2", "test\nerror"),
+            ])
+        );
+
+        // An explicit key is used as the prefix.
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let mut out = KeyValueSerializer::default();
+        Value::serialize(&chain, &dummy_record, "cause", &mut out).unwrap();
+        let keys = out.0.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["cause.0", "cause.1", "cause.2"]);
+    }
+
+    #[test]
+    fn flat_error_chain_options() {
+        let err = DeepError(5);
+        assert_eq!(
+            serialize_kv(&FlatErrorChain::new(&err).max_depth(2)),
+            pairs(&[
+                ("error.0", "depth 5"),
+                ("error.1", "depth 4"),
+                ("error.2", "... (3 more causes)"),
+            ])
+        );
+        assert_eq!(
+            serialize_kv(
+                &FlatErrorChain::new(&err).max_depth(1).emit_root_cause(true)
+            ),
+            pairs(&[
+                ("error.0", "depth 5"),
+                ("error.1", "... (4 more causes)"),
+                ("error.root", "depth 1"),
+                ("error.depth", "5"),
+            ])
+        );
+        assert_eq!(
+            serialize_kv(
+                &FlatErrorChain::new(&err).max_layer_len(5).max_len(9)
+            ),
+            pairs(&[("error.0", "de…"), ("error.1", "d…")])
+        );
+
+        let err = EmbeddingError::Opening(io::Error::other("not found"));
+        assert_eq!(
+            serialize_kv(
+                &FlatErrorChain::new(&err).suppress_double_speak(true)
+            ),
+            pairs(&[("error.0", "opening file"), ("error.1", "not found")])
+        );
+    }
+
+    #[test]
+    fn flat_error_chain_fields() {
+        use crate::fields::tests::{Loading, OpeningFile};
+
+        let err = OpeningFile { path: "/a", err: io::Error::other("denied") };
+        assert_eq!(
+            serialize_kv(&FlatErrorChain::with_fields(&err)),
+            pairs(&[
+                ("error.0", "opening /a"),
+                ("error.1", "denied"),
+                ("error.0.path", "/a"),
+                ("error.0.len", "2"),
+            ])
+        );
+
+        // Fields of registered causes are found by downcasting.
+        crate::register_error_fields::<OpeningFile>();
+        let err = Loading(err);
+        assert_eq!(
+            serialize_kv(&FlatErrorChain::new(&err).with_key("e")),
+            pairs(&[
+                ("e.0", "loading config"),
+                ("e.1", "opening /a"),
+                ("e.2", "denied"),
+                ("e.1.path", "/a"),
+                ("e.1.len", "2"),
+            ])
+        );
+        // As are those of the topmost error, if it is known to be `'static`.
+        assert_eq!(
+            serialize_kv(&FlatErrorChain::from_static(&err.0)),
+            pairs(&[
+                ("error.0", "opening /a"),
+                ("error.1", "denied"),
+                ("error.0.path", "/a"),
+                ("error.0.len", "2"),
+            ])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn flat_error_chain_io_details() {
+        let io_err = io::Error::from_raw_os_error(libc::ENOENT);
+        let message = io_err.to_string();
+        let err = ErrorB::B(ErrorA::A(io_err));
+        assert_eq!(
+            serialize_kv(&FlatErrorChain::new(&err)),
+            [
+                ("error.0", "error b"),
+                ("error.1", "error a"),
+                ("error.2", &message),
+                ("error.2.kind", "NotFound"),
+                ("error.2.raw_os_error", &libc::ENOENT.to_string()),
+                ("error.2.errno", "ENOENT"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+
+        // Errors without an OS error code only have a kind.
+        let io_err = io::Error::new(io::ErrorKind::TimedOut, "test error");
+        let err = ErrorB::B(ErrorA::A(io_err));
+        assert_eq!(
+            serialize_kv(&FlatErrorChain::new(&err)),
+            pairs(&[
+                ("error.0", "error b"),
+                ("error.1", "error a"),
+                ("error.2", "test error"),
+                ("error.2.kind", "TimedOut"),
+            ])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn flat_error_chain_static_io_error() {
+        let err = io::Error::from_raw_os_error(libc::ENOENT);
+        let message = err.to_string();
+
+        // The topmost error can only be downcast if it is known to be
+        // `'static`.
+        assert_eq!(
+            serialize_kv(&FlatErrorChain::new(&err)),
+            pairs(&[("error.0", &message)])
+        );
+        assert_eq!(
+            serialize_kv(&FlatErrorChain::from_static(&err)),
+            pairs(&[
+                ("error.0", &message),
+                ("error.0.kind", "NotFound"),
+                ("error.0.raw_os_error", &libc::ENOENT.to_string()),
+                ("error.0.errno", "ENOENT"),
+            ])
+        );
+    }
+}
@@ -0,0 +1,991 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Rendering options shared by all of the chain adapters, so that
+//! [`InlineErrorChain`](crate::InlineErrorChain), `ArrayErrorChain`, and
+//! `OwnedErrorChain` agree on how a chain looks when printed as a single
+//! string.
+
+use crate::chain;
+use crate::chain::Chain;
+use crate::chain::Truncation;
+use crate::chain::DEFAULT_MAX_DEPTH;
+use crate::fields;
+use crate::fields::ErrorFields;
+use crate::io_error::IoDetails;
+use crate::io_error::WithErrnoName;
+use crate::keys;
+use crate::keys::LayerField;
+use crate::keys::DEFAULT_KEY;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write;
+
+/// Options controlling how a chain of errors is rendered as a single string,
+/// and how it is logged via `slog::KV`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ChainFormat {
+    /// Key used when logging via `slog::KV`.
+    pub(crate) key: &'static str,
+    /// If true, `slog::KV` implementations also log `{key}.root` and
+    /// `{key}.depth`; see [`ChainFormat::emit_root_cause`].
+    pub(crate) root_cause: bool,
+    /// Separator written between each layer of the chain.
+    pub(crate) separator: &'static str,
+    /// Maximum number of layers (including the topmost error) to print. The
+    /// topmost error is always printed, even if this is 0.
+    pub(crate) max_depth: usize,
+    /// If true, each layer is prefixed with its index in the chain (`[0] `,
+    /// `[1] `, ...).
+    pub(crate) prefix_index: bool,
+    /// If true, remove text from each layer that merely repeats the rest of
+    /// the chain; see [`suppress_double_speak`].
+    pub(crate) suppress_double_speak: bool,
+    /// Maximum length in bytes of each layer's message.
+    pub(crate) max_layer_len: Option<usize>,
+    /// Maximum total length in bytes of the rendered chain.
+    pub(crate) max_len: Option<usize>,
+    /// Whether to escape control characters in single-line output; see
+    /// [`Escaped`]. If unset, messages are escaped when logged but not when
+    /// displayed.
+    pub(crate) escape: Option<bool>,
+    /// If true, annotate `io::Error` layers in single-line and report output
+    /// with their OS error code and errno name; see [`WithErrnoName`].
+    pub(crate) errno_names: bool,
+    /// If true, serialize the chain as nested `{"message", "source"}` objects
+    /// rather than as an array; see [`ChainFormat::serialize_layers`].
+    #[cfg(feature = "nested-values")]
+    pub(crate) linked: bool,
+}
+
+impl Default for ChainFormat {
+    fn default() -> Self {
+        Self {
+            key: DEFAULT_KEY,
+            root_cause: false,
+            separator: ": ",
+            max_depth: DEFAULT_MAX_DEPTH,
+            prefix_index: false,
+            suppress_double_speak: false,
+            max_layer_len: None,
+            max_len: None,
+            escape: None,
+            errno_names: false,
+            #[cfg(feature = "nested-values")]
+            linked: false,
+        }
+    }
+}
+
+/// Generates the builder methods that set the options of an adapter's
+/// `format: ChainFormat` field, so that each adapter documents and implements
+/// them identically. Each option may be followed by further doc comments
+/// describing how it applies to that adapter in particular.
+macro_rules! chain_format_options {
+    ($($(#[$attr:meta])* $option:ident),* $(,)?) => {
+        $(chain_format_options!(@option $option $(#[$attr])*);)*
+    };
+    (@option with_key $(#[$attr:meta])*) => {
+        /// Set the key used when logging this chain via its `slog::KV`
+        /// implementation, i.e., when it is logged without an explicit key
+        /// (default: `"error"`).
+        $(#[$attr])*
+        pub fn with_key(mut self, key: &'static str) -> Self {
+            self.format.key = key;
+            self
+        }
+    };
+    (@option emit_root_cause $(#[$attr:meta])*) => {
+        /// If true, the `slog::KV` implementation also logs the message of the
+        /// innermost cause as `{key}.root` and the number of errors in the
+        /// chain as `{key}.depth` (default: false). Both reflect the entire
+        /// chain, regardless of `max_depth`.
+        $(#[$attr])*
+        pub fn emit_root_cause(mut self, emit: bool) -> Self {
+            self.format.root_cause = emit;
+            self
+        }
+    };
+    (@option separator $(#[$attr:meta])*) => {
+        /// Set the separator printed between each error in the chain when it
+        /// is rendered as a single string (default: `": "`).
+        $(#[$attr])*
+        pub fn separator(mut self, separator: &'static str) -> Self {
+            self.format.separator = separator;
+            self
+        }
+    };
+    (@option max_depth $(#[$attr:meta])*) => {
+        /// Print at most `max_depth` errors from the chain, starting with the
+        /// topmost error (default: 1024). If any causes are omitted, a final
+        /// `... (N more causes)` element is printed in their place.
+        $(#[$attr])*
+        pub fn max_depth(mut self, max_depth: usize) -> Self {
+            self.format.max_depth = max_depth;
+            self
+        }
+    };
+    (@option prefix_index $(#[$attr:meta])*) => {
+        /// If true, prefix each error in the chain with its index (`[0] `,
+        /// `[1] `, ...) when it is rendered as a single string (default:
+        /// false).
+        $(#[$attr])*
+        pub fn prefix_index(mut self, prefix_index: bool) -> Self {
+            self.format.prefix_index = prefix_index;
+            self
+        }
+    };
+    (@option suppress_double_speak $(#[$attr:meta])*) => {
+        /// If true, remove "double-speak" from errors whose message embeds the
+        /// message of their source (default: false).
+        ///
+        /// An error whose message is `"opening file: not found"` and whose
+        /// source is `"not found"` is printed as `opening file: not found`
+        /// instead of `opening file: not found: not found`. An error whose
+        /// message is identical to its source's is omitted entirely. Enabling
+        /// this requires allocating a `String` for each error in the chain.
+        $(#[$attr])*
+        pub fn suppress_double_speak(mut self, suppress: bool) -> Self {
+            self.format.suppress_double_speak = suppress;
+            self
+        }
+    };
+    (@option max_layer_len $(#[$attr:meta])*) => {
+        /// Truncate each error's message to at most `max_layer_len` bytes
+        /// (default: unlimited). Truncation happens on a `char` boundary, and
+        /// a truncated message ends with `…` (which counts towards the limit).
+        $(#[$attr])*
+        pub fn max_layer_len(mut self, max_layer_len: usize) -> Self {
+            self.format.max_layer_len = Some(max_layer_len);
+            self
+        }
+    };
+    (@option max_len $(#[$attr:meta])*) => {
+        /// Truncate the entire printed chain to at most `max_len` bytes
+        /// (default: unlimited), in the same manner as `max_layer_len`. A
+        /// precision given when formatting (e.g., `{:.200}`) has the same
+        /// effect; the smaller of the two applies.
+        $(#[$attr])*
+        pub fn max_len(mut self, max_len: usize) -> Self {
+            self.format.max_len = Some(max_len);
+            self
+        }
+    };
+    (@option escape $(#[$attr:meta])*) => {
+        /// If true, escape newlines, tabs, and other control characters in
+        /// each message (as `\n`, `\t`, `\u{1b}`, etc.), which also renders
+        /// ANSI escape sequences inert. Escaping does not apply to the
+        /// multi-line report format.
+        ///
+        /// By default, messages are escaped when logged via the `slog::Value`
+        /// and `slog::KV` implementations, so that an error can't break up or
+        /// spoof log lines, but not when formatted via `Display`.
+        $(#[$attr])*
+        pub fn escape(mut self, escape: bool) -> Self {
+            self.format.escape = Some(escape);
+            self
+        }
+    };
+    (@option errno_names $(#[$attr:meta])*) => {
+        /// If true, annotate each layer that is an `io::Error` with an OS
+        /// error code with that code and its symbolic name, e.g.,
+        /// `No such file or directory (os error 2, ENOENT)` (default: false).
+        /// The name is added to the `(os error N)` that ends the message of
+        /// such an error, and the whole annotation is appended if that's
+        /// missing. Layers are recognized by downcasting, so the topmost error
+        /// is only annotated if the adapter was constructed from a `'static`
+        /// error. Symbolic names are only known on Unix platforms.
+        $(#[$attr])*
+        pub fn errno_names(mut self, errno_names: bool) -> Self {
+            self.format.errno_names = errno_names;
+            self
+        }
+    };
+}
+
+pub(crate) use chain_format_options;
+
+/// The layers of an error chain (the topmost error followed by each of its
+/// causes), which may have been cut short.
+pub(crate) trait Layers: Iterator
+where
+    Self::Item: fmt::Display,
+{
+    /// Once the iterator is exhausted, reports whether (and why) layers were
+    /// omitted.
+    fn truncation(&self) -> Option<Truncation>;
+
+    /// Returns the details of the most recently yielded layer, if it is known
+    /// to be an `io::Error`.
+    fn io_details(&self) -> Option<IoDetails>;
+}
+
+impl<'e> Layers for Chain<'_, dyn Error + 'e> {
+    fn truncation(&self) -> Option<Truncation> {
+        Chain::truncation(self)
+    }
+
+    fn io_details(&self) -> Option<IoDetails> {
+        self.last_static().and_then(IoDetails::of)
+    }
+}
+
+/// Describes a layer passed to the callback of [`ChainFormat::visit_layers`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LayerInfo {
+    /// The index of the layer in the chain.
+    #[cfg_attr(not(feature = "nested-values"), allow(dead_code))]
+    pub(crate) index: usize,
+    /// The details of the layer, if it is an `io::Error`.
+    pub(crate) io: Option<IoDetails>,
+}
+
+impl ChainFormat {
+    /// Options to use when emitting to a [`slog::Serializer`] rather than
+    /// displaying: unless explicitly disabled, control characters are escaped
+    /// so that a message can't break up or spoof log lines.
+    pub(crate) fn for_slog(mut self) -> Self {
+        self.escape.get_or_insert(true);
+        self
+    }
+
+    /// Write `layers` to `f` according to these options, followed by a marker
+    /// if the chain was truncated.
+    ///
+    /// If `f` has the alternate flag set, the chain is written as a
+    /// multi-line report; if it has a precision, that limits the total length
+    /// of the output in the same way as `max_len`.
+    pub(crate) fn write_layers<L>(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        mut layers: L,
+    ) -> fmt::Result
+    where
+        L: Layers,
+        L::Item: fmt::Display,
+    {
+        let report = f.alternate();
+        let max_len = min_limit(self.max_len, f.precision());
+        let mut out = Truncating::new(f, max_len);
+        let result = self.write_layers_to(&mut out, report, &mut layers);
+        out.finish(result)
+    }
+
+    fn write_layers_to<L>(
+        &self,
+        out: &mut dyn fmt::Write,
+        report: bool,
+        layers: &mut L,
+    ) -> fmt::Result
+    where
+        L: Layers,
+        L::Item: fmt::Display,
+    {
+        if self.suppress_double_speak {
+            let mut prepared = Vec::new();
+            let mut io = Vec::new();
+            while let Some(layer) = layers.next() {
+                prepared.push(layer.to_string());
+                io.push(layers.io_details());
+            }
+            let cleaned = suppress_double_speak(prepared);
+            let cleaned =
+                cleaned.into_iter().map(|(index, layer)| (layer, io[index]));
+            self.write_prepared_layers(out, report, cleaned)?;
+        } else {
+            let layers = &mut *layers;
+            let layers = std::iter::from_fn(|| {
+                let layer = layers.next()?;
+                Some((layer, layers.io_details()))
+            });
+            self.write_prepared_layers(out, report, layers)?;
+        }
+        match layers.truncation() {
+            Some(truncation) if report => write!(out, "\n    {truncation}"),
+            Some(truncation) => write!(out, "{}{truncation}", self.separator),
+            None => Ok(()),
+        }
+    }
+
+    /// If enabled, log the message of `err`'s root cause as `{key}.root` and
+    /// the number of errors in its chain as `{key}.depth`. At most 1024 layers
+    /// of the chain are walked; for deeper chains, these describe the deepest
+    /// layer walked.
+    pub(crate) fn emit_root_cause_of(
+        &self,
+        err: &(dyn Error + '_),
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        if !self.root_cause {
+            return Ok(());
+        }
+        let (root, depth) = chain::root_and_depth(err);
+        self.emit_root_cause(&root, depth, serializer)
+    }
+
+    /// Log `root` as `{key}.root` and `depth` as `{key}.depth`. The root
+    /// cause's message is escaped and truncated in the same way as each layer
+    /// of the chain.
+    pub(crate) fn emit_root_cause(
+        &self,
+        root: &dyn fmt::Display,
+        depth: usize,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        let root = FormattedLayer { layer: root, format: self };
+        serializer
+            .emit_arguments(keys::root(self.key), &format_args!("{root}"))?;
+        serializer.emit_usize(keys::depth(self.key), depth)
+    }
+
+    /// Log the [`ErrorFields`] of each layer of `err` (at most `max_depth`)
+    /// as `{key}.{index}.{field}`. `top` provides the fields of `err` itself;
+    /// those of its causes are found via [`crate::register_error_fields`].
+    pub(crate) fn emit_fields_of(
+        &self,
+        err: &dyn Error,
+        top: Option<&dyn ErrorFields>,
+        record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        fields::emit_chain_fields(
+            self.key,
+            err,
+            top,
+            self.max_depth,
+            record,
+            serializer,
+        )
+    }
+
+    /// Write each of `layers` (along with its details, if it is an
+    /// `io::Error`), annotating `io::Error`s with their errno names if
+    /// enabled.
+    fn write_prepared_layers<I, D>(
+        &self,
+        out: &mut dyn fmt::Write,
+        report: bool,
+        layers: I,
+    ) -> fmt::Result
+    where
+        I: IntoIterator<Item = (D, Option<IoDetails>)>,
+        D: fmt::Display,
+    {
+        let layers = layers.into_iter();
+        if self.errno_names {
+            let layers = layers.map(|(layer, io)| WithErrnoName { layer, io });
+            self.write_annotated_layers(out, report, layers)
+        } else {
+            let layers = layers.map(|(layer, _)| layer);
+            self.write_annotated_layers(out, report, layers)
+        }
+    }
+
+    fn write_annotated_layers<I>(
+        &self,
+        out: &mut dyn fmt::Write,
+        report: bool,
+        layers: I,
+    ) -> fmt::Result
+    where
+        I: IntoIterator,
+        I::Item: fmt::Display,
+    {
+        if report {
+            return write_report_layers(out, layers, self.max_layer_len);
+        }
+        for (i, layer) in layers.into_iter().enumerate() {
+            if i > 0 {
+                out.write_str(self.separator)?;
+            }
+            if self.prefix_index {
+                write!(out, "[{i}] ")?;
+            }
+            write!(out, "{}", FormattedLayer { layer: &layer, format: self })?;
+        }
+        Ok(())
+    }
+
+    /// Serialize `layers` as a sequence of strings, one per layer, according
+    /// to these options. If the chain was truncated, a final element
+    /// describing the truncation is appended.
+    ///
+    /// `max_len` limits the total length of the messages in the sequence; once
+    /// it is exhausted, no further elements are serialized. Escaping is left to
+    /// the serializer.
+    ///
+    /// If `linked` is set, the layers are instead serialized as a nested
+    /// object per layer, each holding the next as its `source`:
+    /// `{"message": "outer", "source": {"message": "inner", "source": null}}`.
+    /// This requires collecting the messages into `String`s first.
+    #[cfg(feature = "nested-values")]
+    pub(crate) fn serialize_layers<S, L>(
+        &self,
+        serializer: S,
+        layers: L,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+        L: Layers,
+        L::Item: fmt::Display,
+    {
+        use serde::ser::SerializeSeq;
+
+        let format = Self { escape: Some(false), ..*self };
+        if self.linked {
+            let mut messages = Vec::new();
+            format.visit_layers(layers, |info, layer| {
+                messages.push((layer.to_string(), info.and_then(|i| i.io)));
+                Ok::<_, S::Error>(())
+            })?;
+            return serde::Serialize::serialize(
+                &LinkedLayer(&messages),
+                serializer,
+            );
+        }
+        let mut seq = serializer.serialize_seq(None)?;
+        format.visit_layers(layers, |_, layer| {
+            seq.serialize_element(&format_args!("{layer}"))
+        })?;
+        seq.end()
+    }
+
+    /// Serialize `layers` as a sequence of `{"message": ..., "type": ...}`
+    /// objects, where the type of the layer at index `i` is `type_names[i]`
+    /// (or null if there is no such type name). Otherwise, this behaves like
+    /// [`ChainFormat::serialize_layers`]; the element describing the
+    /// truncation of the chain, if any, has a null type.
+    #[cfg(feature = "nested-values")]
+    pub(crate) fn serialize_layer_objects<S, L>(
+        &self,
+        serializer: S,
+        layers: L,
+        type_names: &[&'static str],
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+        L: Layers,
+        L::Item: fmt::Display,
+    {
+        use serde::ser::SerializeSeq;
+
+        let mut seq = serializer.serialize_seq(None)?;
+        let format = Self { escape: Some(false), ..*self };
+        format.visit_layers(layers, |info, message| {
+            let type_name =
+                info.and_then(|info| type_names.get(info.index)).copied();
+            let io = info.and_then(|info| info.io);
+            seq.serialize_element(&LayerObject { message, type_name, io })
+        })?;
+        seq.end()
+    }
+
+    /// Emit `layers` via `serializer` as separate keys `{key}.0`, `{key}.1`,
+    /// etc., according to these options. If the chain was truncated, a final
+    /// key describing the truncation is emitted. For each `io::Error` layer
+    /// `{key}.N`, its details are emitted as `{key}.N.kind`,
+    /// `{key}.N.raw_os_error`, and `{key}.N.errno`.
+    ///
+    /// `max_len` limits the total length of the emitted messages, as for
+    /// [`ChainFormat::serialize_layers`].
+    pub(crate) fn emit_indexed_layers<L>(
+        &self,
+        key: &str,
+        layers: L,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result
+    where
+        L: Layers,
+        L::Item: fmt::Display,
+    {
+        let mut index = 0;
+        self.visit_layers(layers, |info, layer| {
+            let layer_key = keys::index(key, index);
+            serializer.emit_arguments(layer_key, &format_args!("{layer}"))?;
+            if let Some(io) = info.and_then(|info| info.io) {
+                let field = |field| keys::layer_field(key, index, field);
+                serializer.emit_arguments(
+                    field(LayerField::Kind),
+                    &format_args!("{:?}", io.kind),
+                )?;
+                if let Some(code) = io.raw_os_error {
+                    serializer.emit_i32(field(LayerField::RawOsError), code)?;
+                }
+                if let Some(name) = io.errno_name() {
+                    serializer.emit_str(field(LayerField::Errno), name)?;
+                }
+            }
+            index += 1;
+            Ok(())
+        })
+    }
+
+    /// Call `visit` with a description of each of `layers` (after removing
+    /// double-speak, if enabled) and its message, escaped and truncated
+    /// according to these options, followed by `None` and a description of
+    /// the truncation of the chain, if any.
+    ///
+    /// `max_len` is a budget shared by all layers; once it is exhausted, no
+    /// further layers are visited.
+    fn visit_layers<L, E>(
+        &self,
+        mut layers: L,
+        mut visit: impl FnMut(Option<LayerInfo>, &dyn fmt::Display) -> Result<(), E>,
+    ) -> Result<(), E>
+    where
+        L: Layers,
+        L::Item: fmt::Display,
+    {
+        let mut budget = self.max_len;
+        let mut visit_layer = |info, layer: &dyn fmt::Display| {
+            self.visit_layer(layer, &mut budget, |layer| {
+                visit(Some(info), layer)
+            })
+        };
+        let mut exhausted = false;
+        if self.suppress_double_speak {
+            let mut prepared = Vec::new();
+            let mut io = Vec::new();
+            while let Some(layer) = layers.next() {
+                prepared.push(layer.to_string());
+                io.push(layers.io_details());
+            }
+            for (index, layer) in suppress_double_speak(prepared) {
+                let info = LayerInfo { index, io: io[index] };
+                if !visit_layer(info, &layer)? {
+                    exhausted = true;
+                    break;
+                }
+            }
+        } else {
+            let mut index = 0;
+            while let Some(layer) = layers.next() {
+                let info = LayerInfo { index, io: layers.io_details() };
+                if !visit_layer(info, &layer)? {
+                    exhausted = true;
+                    break;
+                }
+                index += 1;
+            }
+        }
+        match (exhausted, layers.truncation()) {
+            (false, Some(truncation)) => visit(None, &truncation),
+            _ => Ok(()),
+        }
+    }
+
+    /// Visit a single layer, deducting its length from `budget`. Returns
+    /// false if the budget has been exhausted.
+    fn visit_layer<E>(
+        &self,
+        layer: &dyn fmt::Display,
+        budget: &mut Option<usize>,
+        mut visit: impl FnMut(&dyn fmt::Display) -> Result<(), E>,
+    ) -> Result<bool, E> {
+        let escaped;
+        let layer = if self.escape.unwrap_or(false) {
+            escaped = Escaped(layer);
+            &escaped as &dyn fmt::Display
+        } else {
+            layer
+        };
+        let limit = min_limit(self.max_layer_len, *budget);
+        let Some(limit) = limit else {
+            visit(layer)?;
+            return Ok(true);
+        };
+        let (message, truncated) = limited_string(layer, Some(limit));
+        visit(&message)?;
+        let Some(remaining) = budget else {
+            return Ok(true);
+        };
+        let cut_by_budget = truncated && Some(limit) == Some(*remaining);
+        *remaining = remaining.saturating_sub(message.len());
+        Ok(*remaining > 0 && !cut_by_budget)
+    }
+}
+
+/// The remaining layers of a chain (with their details, if they are
+/// `io::Error`s), serialized as nested `{"message": ..., "source": ...}`
+/// objects (or null if there are none).
+#[cfg(feature = "nested-values")]
+struct LinkedLayer<'a>(&'a [(String, Option<IoDetails>)]);
+
+#[cfg(feature = "nested-values")]
+impl serde::Serialize for LinkedLayer<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let Some(((message, io), rest)) = self.0.split_first() else {
+            return serializer.serialize_none();
+        };
+        let len = 2 + io_field_count(*io);
+        let mut object = serializer.serialize_struct("Layer", len)?;
+        object.serialize_field("message", message)?;
+        serialize_io_fields(&mut object, *io)?;
+        object.serialize_field("source", &LinkedLayer(rest))?;
+        object.end()
+    }
+}
+
+/// A single layer of a chain, serialized as
+/// `{"message": ..., "type": ...}`, along with its details if it is an
+/// `io::Error`.
+#[cfg(feature = "nested-values")]
+struct LayerObject<'a> {
+    message: &'a dyn fmt::Display,
+    type_name: Option<&'static str>,
+    io: Option<IoDetails>,
+}
+
+#[cfg(feature = "nested-values")]
+impl serde::Serialize for LayerObject<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let len = 2 + io_field_count(self.io);
+        let mut object = serializer.serialize_struct("Layer", len)?;
+        object.serialize_field("message", &format_args!("{}", self.message))?;
+        object.serialize_field("type", &self.type_name)?;
+        serialize_io_fields(&mut object, self.io)?;
+        object.end()
+    }
+}
+
+/// Returns the number of fields written by [`serialize_io_fields`].
+#[cfg(feature = "nested-values")]
+fn io_field_count(io: Option<IoDetails>) -> usize {
+    io.map_or(0, |io| {
+        1 + usize::from(io.raw_os_error.is_some())
+            + usize::from(io.errno_name().is_some())
+    })
+}
+
+/// Serialize the `kind`, `raw_os_error`, and `errno` fields of an `io::Error`
+/// layer; the latter two are omitted if the error has no OS error code (or its
+/// name is unknown).
+#[cfg(feature = "nested-values")]
+fn serialize_io_fields<S>(
+    object: &mut S,
+    io: Option<IoDetails>,
+) -> Result<(), S::Error>
+where
+    S: serde::ser::SerializeStruct,
+{
+    let Some(io) = io else {
+        return Ok(());
+    };
+    object.serialize_field("kind", &format_args!("{:?}", io.kind))?;
+    if let Some(code) = io.raw_os_error {
+        object.serialize_field("raw_os_error", &code)?;
+    }
+    if let Some(name) = io.errno_name() {
+        object.serialize_field("errno", name)?;
+    }
+    Ok(())
+}
+
+/// Displays a single layer of a chain in single-line form, escaped and
+/// truncated according to `format`.
+struct FormattedLayer<'a> {
+    layer: &'a dyn fmt::Display,
+    format: &'a ChainFormat,
+}
+
+impl fmt::Display for FormattedLayer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max_layer_len = self.format.max_layer_len;
+        if self.format.escape.unwrap_or(false) {
+            write_limited(f, &Escaped(self.layer), max_layer_len)
+        } else {
+            write_limited(f, self.layer, max_layer_len)
+        }
+    }
+}
+
+/// Combine two optional length limits, keeping the smaller.
+fn min_limit(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Write `layer` to `out`, truncated to at most `limit` bytes.
+fn write_limited(
+    out: &mut dyn fmt::Write,
+    layer: &dyn fmt::Display,
+    limit: Option<usize>,
+) -> fmt::Result {
+    let mut out = Truncating::new(out, limit);
+    let result = write!(out, "{layer}");
+    out.finish(result)
+}
+
+/// Render `layer` to a string of at most `limit` bytes, also returning whether
+/// it was truncated.
+fn limited_string(
+    layer: &dyn fmt::Display,
+    limit: Option<usize>,
+) -> (String, bool) {
+    let mut s = String::new();
+    let mut out = Truncating::new(&mut s, limit);
+    let result = write!(out, "{layer}");
+    let truncated = out.truncated;
+    // As with `ToString`, an error here can only come from `layer`'s `Display`
+    // implementation; keep whatever it wrote.
+    let _ = out.finish(result);
+    (s, truncated)
+}
+
+/// Write `layers` as a multi-line report: the topmost error on the first line,
+/// followed by a numbered, indented list of its causes:
+///
+/// ```text
+/// outer error
+///
+/// Caused by:
+///     0: middle error
+///     1: inner error
+/// ```
+///
+/// Continuation lines of multi-line messages are indented to line up with the
+/// first line of their message.
+fn write_report_layers<I>(
+    out: &mut dyn fmt::Write,
+    layers: I,
+    max_layer_len: Option<usize>,
+) -> fmt::Result
+where
+    I: IntoIterator,
+    I::Item: fmt::Display,
+{
+    let mut layers = layers.into_iter();
+    if let Some(top) = layers.next() {
+        write_limited(out, &top, max_layer_len)?;
+    }
+    for (i, layer) in layers.enumerate() {
+        if i == 0 {
+            write!(out, "\n\nCaused by:")?;
+        }
+        let prefix = format!("    {i}: ");
+        write!(out, "\n{prefix}")?;
+        let mut indented = Indented {
+            out: &mut *out,
+            indent: prefix.len(),
+            at_line_start: false,
+        };
+        write_limited(&mut indented, &layer, max_layer_len)?;
+    }
+    Ok(())
+}
+
+/// [`fmt::Write`] adapter that indents every line after the first by `indent`
+/// spaces. Empty lines are left empty.
+struct Indented<'a> {
+    out: &'a mut dyn fmt::Write,
+    indent: usize,
+    at_line_start: bool,
+}
+
+impl fmt::Write for Indented<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.out.write_char('\n')?;
+                self.at_line_start = true;
+            }
+            if !line.is_empty() {
+                if self.at_line_start {
+                    write!(self.out, "{:1$}", "", self.indent)?;
+                    self.at_line_start = false;
+                }
+                self.out.write_str(line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adapter that displays its contents with newlines, tabs, and other control
+/// characters escaped (`\n`, `\r`, `\t`, or `\u{..}`), so that they can't
+/// break up line-oriented logs. This includes the ESC character that begins
+/// ANSI escape sequences, which are thereby rendered inert.
+struct Escaped<D>(D);
+
+impl<D: fmt::Display> fmt::Display for Escaped<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(Escaping(f), "{}", self.0)
+    }
+}
+
+/// [`fmt::Write`] adapter backing [`Escaped`].
+struct Escaping<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl fmt::Write for Escaping<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut rest = s;
+        while let Some(i) = rest.find(char::is_control) {
+            self.0.write_str(&rest[..i])?;
+            let c = rest[i..].chars().next().unwrap();
+            match c {
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                '\t' => self.0.write_str("\\t")?,
+                c => write!(self.0, "\\u{{{:x}}}", c as u32)?,
+            }
+            rest = &rest[i + c.len_utf8()..];
+        }
+        self.0.write_str(rest)
+    }
+}
+
+/// Marker written in place of text removed by [`Truncating`].
+const ELLIPSIS: &str = "…";
+
+/// [`fmt::Write`] adapter that writes at most `limit` bytes (if a limit is
+/// given), cutting on a `char` boundary and ending with [`ELLIPSIS`] if any
+/// text was removed. The ellipsis counts towards the limit.
+///
+/// Once the limit is exceeded, writes fail with [`fmt::Error`] so callers stop
+/// formatting early; [`Truncating::finish()`] converts that back into success.
+struct Truncating<W> {
+    inner: W,
+    limit: Option<usize>,
+    written: usize,
+    // Text that fits within the limit but not alongside the ellipsis; it is
+    // held back until we know whether the ellipsis is needed.
+    pending: String,
+    truncated: bool,
+}
+
+impl<W: fmt::Write> Truncating<W> {
+    fn new(inner: W, limit: Option<usize>) -> Self {
+        Self {
+            inner,
+            limit,
+            written: 0,
+            pending: String::new(),
+            truncated: false,
+        }
+    }
+
+    /// Complete writing, given the `result` of formatting into `self`.
+    fn finish(mut self, result: fmt::Result) -> fmt::Result {
+        match result {
+            Err(_) if self.truncated => Ok(()),
+            Err(err) => Err(err),
+            Ok(()) => self.inner.write_str(&self.pending),
+        }
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for Truncating<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let Some(limit) = self.limit else {
+            return self.inner.write_str(s);
+        };
+        if self.truncated {
+            return Err(fmt::Error);
+        }
+        let reserve = if limit >= ELLIPSIS.len() { ELLIPSIS.len() } else { 0 };
+        self.pending.push_str(s);
+
+        let available = limit - self.written;
+        if self.pending.len() <= available {
+            // Everything still fits; write out all but the tail that may need
+            // to be replaced by the ellipsis.
+            let n = floor_char_boundary(
+                &self.pending,
+                (limit - reserve).saturating_sub(self.written),
+            );
+            self.inner.write_str(&self.pending[..n])?;
+            self.written += n;
+            self.pending.drain(..n);
+            Ok(())
+        } else {
+            let n = floor_char_boundary(
+                &self.pending,
+                available.saturating_sub(reserve),
+            );
+            self.inner.write_str(&self.pending[..n])?;
+            self.inner.write_str(&ELLIPSIS[..reserve])?;
+            self.pending.clear();
+            self.truncated = true;
+            Err(fmt::Error)
+        }
+    }
+}
+
+/// Returns the largest `char` boundary in `s` that is at most `index`.
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+    (0..=index).rev().find(|&i| s.is_char_boundary(i)).unwrap_or(0)
+}
+
+/// Remove "double-speak" from a chain of rendered layers, returning the
+/// remaining layers along with their index in the original chain.
+///
+/// Error types that embed their source in their own message (e.g.,
+/// `#[error("opening file: {err}")]`) cause the source's text to be printed
+/// twice when the whole chain is walked. Working from the innermost cause
+/// outward, if a layer's message equals the remainder of the chain (or the
+/// message of its immediate cause), the layer is dropped entirely; if it ends
+/// with `: ` followed by that text, the redundant suffix is removed.
+pub(crate) fn suppress_double_speak(
+    layers: Vec<String>,
+) -> Vec<(usize, String)> {
+    // Cleaned layers, innermost first.
+    let mut cleaned: Vec<(usize, String)> = Vec::with_capacity(layers.len());
+    for (i, layer) in layers.iter().enumerate().rev() {
+        let Some(next) = layers.get(i + 1) else {
+            cleaned.push((i, layer.clone()));
+            continue;
+        };
+        let rest = cleaned
+            .iter()
+            .rev()
+            .map(|(_, layer)| layer.as_str())
+            .collect::<Vec<_>>()
+            .join(": ");
+
+        // Prefer stripping the longer of the two candidates, so that a layer
+        // that embeds the full remainder of the chain is stripped entirely.
+        let (long, short) = if rest.len() >= next.len() {
+            (rest.as_str(), next.as_str())
+        } else {
+            (next.as_str(), rest.as_str())
+        };
+        match strip_embedded(layer, long)
+            .or_else(|| strip_embedded(layer, short))
+        {
+            Some("") => (),
+            Some(stripped) => cleaned.push((i, stripped.to_string())),
+            None => cleaned.push((i, layer.clone())),
+        }
+    }
+    cleaned.reverse();
+    cleaned
+}
+
+/// If `message` is `embedded` or ends with `: {embedded}`, return the
+/// remaining prefix (which is empty in the former case).
+fn strip_embedded<'a>(message: &'a str, embedded: &str) -> Option<&'a str> {
+    if embedded.is_empty() {
+        return None;
+    }
+    let prefix = message.strip_suffix(embedded)?.trim_end();
+    if prefix.is_empty() {
+        return Some("");
+    }
+    prefix.strip_suffix(':').map(str::trim_end)
+}
@@ -0,0 +1,225 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Recognition of [`io::Error`] layers, so that their kind and OS error code
+//! can be logged alongside their message.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Details of an [`io::Error`] layer of a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IoDetails {
+    pub(crate) kind: io::ErrorKind,
+    pub(crate) raw_os_error: Option<i32>,
+}
+
+impl IoDetails {
+    /// Returns the details of `err` if it is an `io::Error` that has any:
+    /// custom errors of kind `Other` (e.g., from `io::Error::other`) carry
+    /// nothing beyond their message.
+    pub(crate) fn of(err: &(dyn Error + 'static)) -> Option<Self> {
+        let err = err.downcast_ref::<io::Error>()?;
+        let details =
+            Self { kind: err.kind(), raw_os_error: err.raw_os_error() };
+        let uninformative = details.kind == io::ErrorKind::Other
+            && details.raw_os_error.is_none();
+        (!uninformative).then_some(details)
+    }
+
+    /// Returns the symbolic name of the OS error code (e.g., `ENOENT`), if
+    /// any.
+    pub(crate) fn errno_name(&self) -> Option<&'static str> {
+        self.raw_os_error.and_then(errno_name)
+    }
+}
+
+/// Returns the symbolic name of the errno value `code` (e.g., `ENOENT` for 2
+/// on most platforms), if it is one of the errors defined by POSIX.
+#[cfg(unix)]
+pub(crate) fn errno_name(code: i32) -> Option<&'static str> {
+    macro_rules! errno_names {
+        ($($name:ident),* $(,)?) => {
+            [$((libc::$name, stringify!($name))),*]
+        };
+    }
+    // Some of these share a value on some platforms (e.g., `EAGAIN` and
+    // `EWOULDBLOCK`); the first listed wins.
+    let names = errno_names!(
+        E2BIG,
+        EACCES,
+        EADDRINUSE,
+        EADDRNOTAVAIL,
+        EAFNOSUPPORT,
+        EAGAIN,
+        EALREADY,
+        EBADF,
+        EBADMSG,
+        EBUSY,
+        ECANCELED,
+        ECHILD,
+        ECONNABORTED,
+        ECONNREFUSED,
+        ECONNRESET,
+        EDEADLK,
+        EDESTADDRREQ,
+        EDOM,
+        EDQUOT,
+        EEXIST,
+        EFAULT,
+        EFBIG,
+        EHOSTUNREACH,
+        EIDRM,
+        EILSEQ,
+        EINPROGRESS,
+        EINTR,
+        EINVAL,
+        EIO,
+        EISCONN,
+        EISDIR,
+        ELOOP,
+        EMFILE,
+        EMLINK,
+        EMSGSIZE,
+        ENAMETOOLONG,
+        ENETDOWN,
+        ENETRESET,
+        ENETUNREACH,
+        ENFILE,
+        ENOBUFS,
+        ENODEV,
+        ENOENT,
+        ENOEXEC,
+        ENOLCK,
+        ENOMEM,
+        ENOMSG,
+        ENOPROTOOPT,
+        ENOSPC,
+        ENOSYS,
+        ENOTCONN,
+        ENOTDIR,
+        ENOTEMPTY,
+        ENOTSOCK,
+        ENOTSUP,
+        ENOTTY,
+        ENXIO,
+        EOPNOTSUPP,
+        EOVERFLOW,
+        EPERM,
+        EPIPE,
+        EPROTO,
+        EPROTONOSUPPORT,
+        EPROTOTYPE,
+        ERANGE,
+        EROFS,
+        ESPIPE,
+        ESRCH,
+        ESTALE,
+        ETIMEDOUT,
+        ETXTBSY,
+        EWOULDBLOCK,
+        EXDEV,
+    );
+    names.iter().find(|(value, _)| *value == code).map(|(_, name)| *name)
+}
+
+/// Returns the symbolic name of the errno value `code`; symbolic names are
+/// only known on Unix platforms.
+#[cfg(not(unix))]
+pub(crate) fn errno_name(_code: i32) -> Option<&'static str> {
+    None
+}
+
+/// Displays the message of a layer of a chain, annotated with the OS error
+/// code and errno name given by `io` (the details of the layer, if it is an
+/// `io::Error`): e.g., `Permission denied (os error 13, EACCES)`.
+///
+/// If the message already ends in `(os error N)` for the same code, as those of
+/// `io::Error`s do, the name is added to that; otherwise, the annotation is
+/// appended. Layers without an OS error code are displayed unchanged,
+/// whatever their message.
+pub(crate) struct WithErrnoName<D> {
+    pub(crate) layer: D,
+    pub(crate) io: Option<IoDetails>,
+}
+
+impl<D: fmt::Display> fmt::Display for WithErrnoName<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(code) = self.io.and_then(|io| io.raw_os_error) else {
+            return write!(f, "{}", self.layer);
+        };
+        let plain = format!("(os error {code})");
+        let annotation = match errno_name(code) {
+            Some(name) => format!("(os error {code}, {name})"),
+            None => plain.clone(),
+        };
+        let message = self.layer.to_string();
+        if message.ends_with(&annotation) {
+            f.write_str(&message)
+        } else if let Some(prefix) = message.strip_suffix(&plain) {
+            write!(f, "{prefix}{annotation}")
+        } else if message.is_empty() {
+            f.write_str(&annotation)
+        } else {
+            write!(f, "{message} {annotation}")
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_details() {
+        let err = io::Error::from_raw_os_error(libc::ENOENT);
+        let details = IoDetails::of(&err).unwrap();
+        assert_eq!(details.kind, io::ErrorKind::NotFound);
+        assert_eq!(details.raw_os_error, Some(libc::ENOENT));
+        assert_eq!(details.errno_name(), Some("ENOENT"));
+
+        let err = io::Error::new(io::ErrorKind::TimedOut, "custom");
+        let details = IoDetails::of(&err).unwrap();
+        assert_eq!(details.kind, io::ErrorKind::TimedOut);
+        assert_eq!(details.errno_name(), None);
+
+        let err = io::Error::other("custom");
+        assert_eq!(IoDetails::of(&err), None);
+
+        let err = std::fmt::Error;
+        assert_eq!(IoDetails::of(&err), None);
+    }
+
+    #[test]
+    fn with_errno_name() {
+        let annotate = |layer: &dyn fmt::Display, err: &io::Error| {
+            let io = IoDetails::of(err);
+            WithErrnoName { layer, io }.to_string()
+        };
+        let err = io::Error::from_raw_os_error(libc::EACCES);
+        let annotated =
+            format!("Permission denied (os error {}, EACCES)", libc::EACCES);
+        assert_eq!(annotate(&err, &err), annotated);
+        assert_eq!(annotate(&annotated, &err), annotated);
+        // The annotation is appended to messages without it.
+        assert_eq!(
+            annotate(&"access denied", &err),
+            format!("access denied (os error {}, EACCES)", libc::EACCES)
+        );
+
+        // Other layers are left alone, even if their message looks like that
+        // of an `io::Error`.
+        let err = io::Error::new(io::ErrorKind::TimedOut, "custom");
+        assert_eq!(
+            annotate(&"custom (os error 2)", &err),
+            "custom (os error 2)"
+        );
+        let err = io::Error::other("custom");
+        assert_eq!(
+            annotate(&"custom (os error 2)", &err),
+            "custom (os error 2)"
+        );
+    }
+}
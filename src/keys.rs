@@ -0,0 +1,264 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Construction of derived `slog` keys, such as `error.root` from `error`.
+//!
+//! The keys derived from the default key (`error.root`, `error.0`,
+//! `error.0.kind`, etc.) are precomputed. Others are built when they're
+//! logged.
+//!
+//! Unless slog is built with the `dynamic-keys` feature, keys must be
+//! `&'static str`s, so built keys are interned: each distinct key is leaked the
+//! first time it is built and reused thereafter. Interned keys are looked up by
+//! the components they're built from, so logging a key that has been built
+//! before neither formats nor allocates it. Every key they're derived from
+//! (including those logged by `ErrorFields` implementations) is then itself a
+//! `&'static str` written in the program, so there are only so many distinct
+//! derived keys, and the leaked memory is bounded. With `dynamic-keys`, keys
+//! may be built at runtime, so there could be any number of derived keys:
+//! they're built as owned `slog::Key`s instead, and nothing is interned.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::RwLock;
+
+/// Key used by the `slog::KV` implementations in this crate unless another is
+/// chosen via `with_key()`.
+pub(crate) const DEFAULT_KEY: &str = "error";
+
+macro_rules! default_keys {
+    ($($index:literal)*) => {
+        /// `error.{index}`, for each index of the precomputed keys.
+        const INDEX_KEYS: &[&str] = &[$(concat!("error.", $index)),*];
+        /// `error.{index}.kind`.
+        const KIND_KEYS: &[&str] = &[$(concat!("error.", $index, ".kind")),*];
+        /// `error.{index}.raw_os_error`.
+        const RAW_OS_ERROR_KEYS: &[&str] =
+            &[$(concat!("error.", $index, ".raw_os_error")),*];
+        /// `error.{index}.errno`.
+        const ERRNO_KEYS: &[&str] =
+            &[$(concat!("error.", $index, ".errno")),*];
+    };
+}
+
+// Enough for the layers of all but unusually deep chains; keys for deeper
+// layers are built when they're logged.
+default_keys!(
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27
+    28 29 30 31 32
+);
+
+/// A key describing a layer of a chain; see [`layer_field`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LayerField {
+    Kind,
+    RawOsError,
+    Errno,
+}
+
+impl LayerField {
+    fn name(self) -> &'static str {
+        match self {
+            LayerField::Kind => "kind",
+            LayerField::RawOsError => "raw_os_error",
+            LayerField::Errno => "errno",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            LayerField::Kind => KIND_KEYS,
+            LayerField::RawOsError => RAW_OS_ERROR_KEYS,
+            LayerField::Errno => ERRNO_KEYS,
+        }
+    }
+}
+
+/// Returns the key `{prefix}.root`.
+pub(crate) fn root(prefix: &str) -> slog::Key {
+    match prefix {
+        DEFAULT_KEY => static_key("error.root"),
+        _ => build(Parts { prefix, index: None, suffix: "root" }),
+    }
+}
+
+/// Returns the key `{prefix}.depth`.
+pub(crate) fn depth(prefix: &str) -> slog::Key {
+    match prefix {
+        DEFAULT_KEY => static_key("error.depth"),
+        _ => build(Parts { prefix, index: None, suffix: "depth" }),
+    }
+}
+
+/// Returns the key `{prefix}.{index}`.
+pub(crate) fn index(prefix: &str, index: usize) -> slog::Key {
+    match (prefix, INDEX_KEYS.get(index)) {
+        (DEFAULT_KEY, Some(key)) => static_key(key),
+        _ => build(Parts { prefix, index: Some(index), suffix: "" }),
+    }
+}
+
+/// Returns the key `{prefix}.{index}.{field}`.
+pub(crate) fn layer_field(
+    prefix: &str,
+    index: usize,
+    field: LayerField,
+) -> slog::Key {
+    match (prefix, field.default_keys().get(index)) {
+        (DEFAULT_KEY, Some(key)) => static_key(key),
+        _ => build(Parts { prefix, index: Some(index), suffix: field.name() }),
+    }
+}
+
+/// Returns the key `{prefix}.{index}.{key}`, for a key logged by the
+/// `ErrorFields` of the layer at `index`.
+pub(crate) fn field(prefix: &str, index: usize, key: &str) -> slog::Key {
+    build(Parts { prefix, index: Some(index), suffix: key })
+}
+
+/// The components of the key `{prefix}.{index}.{suffix}`, where `.{index}` is
+/// omitted if `index` is `None` and `.{suffix}` if `suffix` is empty.
+#[derive(Debug, Clone, Copy)]
+struct Parts<'a> {
+    prefix: &'a str,
+    index: Option<usize>,
+    suffix: &'a str,
+}
+
+impl fmt::Display for Parts<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.prefix)?;
+        if let Some(index) = self.index {
+            write!(f, ".{index}")?;
+        }
+        if !self.suffix.is_empty() {
+            write!(f, ".{}", self.suffix)?;
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::useless_conversion)] // see InlineErrorChain's KV impl
+fn static_key(key: &'static str) -> slog::Key {
+    key.into()
+}
+
+/// Returns the key built from `parts` as an owned `slog::Key` if slog is built
+/// with `dynamic-keys`, or else interned.
+// The borrow is only needless if slog is built with `dynamic-keys`.
+#[allow(clippy::needless_borrow)]
+fn build(parts: Parts<'_>) -> slog::Key {
+    // Which of these applies depends on the type of `slog::Key`: the method
+    // of `Owned` takes precedence, as it doesn't require auto-referencing the
+    // receiver, but it only applies if a `slog::Key` can be built from a
+    // `String`.
+    (&KeyType::<slog::Key>(PhantomData)).build(parts)
+}
+
+struct KeyType<K>(PhantomData<K>);
+
+// Only used if slog is built with `dynamic-keys`.
+#[allow(dead_code)]
+trait Owned {
+    fn build(&self, parts: Parts<'_>) -> slog::Key;
+}
+
+impl<K: From<String> + Into<slog::Key>> Owned for KeyType<K> {
+    fn build(&self, parts: Parts<'_>) -> slog::Key {
+        K::from(parts.to_string()).into()
+    }
+}
+
+// Only used unless slog is built with `dynamic-keys`.
+#[allow(dead_code)]
+trait Interned {
+    fn build(&self, parts: Parts<'_>) -> slog::Key;
+}
+
+impl<K> Interned for &KeyType<K> {
+    fn build(&self, parts: Parts<'_>) -> slog::Key {
+        static_key(intern(parts))
+    }
+}
+
+/// Interned keys, by prefix, index and suffix.
+type InternedKeys = BTreeMap<
+    Box<str>,
+    BTreeMap<Option<usize>, BTreeMap<Box<str>, &'static str>>,
+>;
+
+/// Returns the key built from `parts` as a `&'static str`, leaking it unless
+/// the same key has been interned before.
+#[allow(dead_code)] // as for `Interned`
+fn intern(parts: Parts<'_>) -> &'static str {
+    static KEYS: RwLock<InternedKeys> = RwLock::new(BTreeMap::new());
+
+    let Parts { prefix, index, suffix } = parts;
+    let keys = KEYS.read().unwrap_or_else(|err| err.into_inner());
+    let interned = keys
+        .get(prefix)
+        .and_then(|keys| keys.get(&index))
+        .and_then(|keys| keys.get(suffix));
+    if let Some(key) = interned {
+        return key;
+    }
+    drop(keys);
+
+    // Another thread may have interned the key since it was looked up, hence
+    // the `entry()` calls.
+    let mut keys = KEYS.write().unwrap_or_else(|err| err.into_inner());
+    keys.entry(prefix.into())
+        .or_default()
+        .entry(index)
+        .or_default()
+        .entry(suffix.into())
+        .or_insert_with(|| Box::leak(parts.to_string().into_boxed_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_reuses_keys() {
+        let parts = Parts { prefix: "err", index: None, suffix: "root" };
+        let a = intern(parts);
+        let b = intern(parts);
+        assert_eq!(a, "err.root");
+        assert!(std::ptr::eq(a, b));
+        let parts = Parts { prefix: "err", index: Some(2), suffix: "path" };
+        assert_eq!(intern(parts), "err.2.path");
+        let parts = Parts { prefix: "err", index: Some(2), suffix: "" };
+        assert_eq!(intern(parts), "err.2");
+    }
+
+    #[test]
+    fn derived_keys() {
+        assert_eq!(root(DEFAULT_KEY), "error.root");
+        assert_eq!(depth(DEFAULT_KEY), "error.depth");
+        assert_eq!(root("err"), "err.root");
+        assert_eq!(depth("err"), "err.depth");
+        assert_eq!(field(DEFAULT_KEY, 1, "path"), "error.1.path");
+        assert_eq!(field(DEFAULT_KEY, 40, "path"), "error.40.path");
+        assert_eq!(field("err", 0, "path"), "err.0.path");
+        for i in 0..40 {
+            assert_eq!(index(DEFAULT_KEY, i), format!("{DEFAULT_KEY}.{i}"));
+            assert_eq!(index("err", i), format!("err.{i}"));
+            for field in
+                [LayerField::Kind, LayerField::RawOsError, LayerField::Errno]
+            {
+                let name = field.name();
+                assert_eq!(
+                    layer_field(DEFAULT_KEY, i, field),
+                    format!("{DEFAULT_KEY}.{i}.{name}")
+                );
+                assert_eq!(
+                    layer_field("err", i, field),
+                    format!("err.{i}.{name}")
+                );
+            }
+        }
+    }
+}
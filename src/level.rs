@@ -0,0 +1,145 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`SlogErrorLevel`] lets errors declare the level at which they should be
+//! logged, which [`log_error!`](crate::log_error) respects.
+
+/// Reports the level at which an error should be logged.
+///
+/// Not every error is worth an `ERROR` record wherever it propagates: e.g., a
+/// client disconnecting mid-request is expected, and is better logged as a
+/// warning. The derive macros implement this trait for every type, using
+/// `#[slog(level = "...")]` on the type or (for enums) on each variant, and
+/// `slog::Level::Error` by default.
+pub trait SlogErrorLevel {
+    /// The level at which `self` should be logged.
+    fn log_level(&self) -> slog::Level;
+}
+
+/// Log a message along with an error chain, at the level given by the error's
+/// [`SlogErrorLevel`] implementation.
+///
+/// This takes the logger, a reference to the error, and then a message and
+/// key/value pairs, and logs them as [`log_chain!`](crate::log_chain) does:
+///
+/// ```
+/// # use slog_error_chain::{log_error, SlogErrorLevel};
+/// # #[derive(Debug, thiserror::Error)]
+/// # #[error("client disconnected")]
+/// # struct Disconnected;
+/// # impl SlogErrorLevel for Disconnected {
+/// #     fn log_level(&self) -> slog::Level { slog::Level::Warning }
+/// # }
+/// # let log = slog::Logger::root(slog::Discard, slog::o!());
+/// # let err = Disconnected;
+/// let request_id = 7;
+/// log_error!(log, &err, "request {request_id} failed"; "retries" => 3);
+/// ```
+#[macro_export]
+macro_rules! log_error {
+    ($log:expr, $err:expr, $($args:tt)+) => {{
+        let err = $err;
+        $crate::log_chain!(
+            $log,
+            $crate::SlogErrorLevel::log_level(err),
+            err,
+            $($args)+
+        )
+    }};
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tests::KeyValueSerializer;
+    use slog::Drain;
+    use slog::KV;
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    /// The level, message, and key/value pairs (as `key=value` strings) of a
+    /// logged record.
+    pub(crate) type Logged = (slog::Level, String, Vec<String>);
+
+    /// Drain that records each record as a [`Logged`].
+    #[derive(Clone, Default)]
+    pub(crate) struct Records(Arc<Mutex<Vec<Logged>>>);
+
+    impl Drain for Records {
+        type Ok = ();
+        type Err = slog::Never;
+
+        fn log(
+            &self,
+            record: &slog::Record,
+            _values: &slog::OwnedKVList,
+        ) -> Result<(), slog::Never> {
+            let mut out = KeyValueSerializer::default();
+            record.kv().serialize(record, &mut out).unwrap();
+            let kvs = out.0.into_iter().map(|(k, v)| format!("{k}={v}"));
+            self.0.lock().unwrap().push((
+                record.level(),
+                record.msg().to_string(),
+                kvs.collect(),
+            ));
+            Ok(())
+        }
+    }
+
+    impl Records {
+        pub(crate) fn logger(&self) -> slog::Logger {
+            slog::Logger::root(self.clone(), slog::o!())
+        }
+
+        pub(crate) fn take(&self) -> Vec<Logged> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("request failed")]
+    struct RequestError {
+        level: slog::Level,
+        #[source]
+        source: io::Error,
+    }
+
+    impl SlogErrorLevel for RequestError {
+        fn log_level(&self) -> slog::Level {
+            self.level
+        }
+    }
+
+    #[test]
+    fn log_error_levels() {
+        let records = Records::default();
+        let log = records.logger();
+        let err = |level| RequestError {
+            level,
+            source: io::Error::other("client disconnected"),
+        };
+
+        let id = 7;
+        log_error!(log, &err(slog::Level::Warning), "handling {}", id);
+        log_error!(log, &err(slog::Level::Error), "handling {id}"; "id" => id);
+        log_error!(log, &err(slog::Level::Info), "handling"; "id" => id,);
+        let chain = "error=request failed: client disconnected";
+        let logged = |level, msg: &str, kvs: &[&str]| -> Logged {
+            (
+                level,
+                msg.to_string(),
+                kvs.iter().map(|kv| kv.to_string()).collect(),
+            )
+        };
+        assert_eq!(
+            records.take(),
+            [
+                logged(slog::Level::Warning, "handling 7", &[chain]),
+                logged(slog::Level::Error, "handling 7", &["id=7", chain]),
+                logged(slog::Level::Info, "handling", &["id=7", chain]),
+            ]
+        );
+    }
+}
@@ -4,50 +4,180 @@
 
 //! `slog-error-chain` provides `Display` and `slog::Value` adapters to report
 //! the full chain of error causes from `std::error::Error`s.
+//!
+//! The [`chain()`] function exposes the same (cycle-safe) walk of an error's
+//! `source()` chain used by the adapters, for code that needs to inspect the
+//! causes of an error directly.
 
+use format::chain_format_options;
+use format::ChainFormat;
 use slog::Value;
 use slog::KV;
 use std::error::Error;
 use std::fmt;
 
+mod chain;
+mod ext;
+mod fields;
+mod flat;
+mod format;
+mod io_error;
+mod keys;
+mod level;
+mod macros;
+mod type_names;
+
+pub use chain::chain;
+pub use chain::Chain;
+pub use chain::Truncation;
+pub use ext::ErrorChainExt;
+pub use ext::ResultExt;
+pub use fields::register_error_fields;
+pub use fields::ErrorFields;
+pub use flat::FlatErrorChain;
+pub use level::SlogErrorLevel;
+pub use type_names::ErrorTypeNames;
+
+#[cfg(feature = "anyhow")]
+mod anyhow_error;
+#[cfg(all(feature = "anyhow", feature = "nested-values"))]
+pub use anyhow_error::ArrayAnyhowChain;
+#[cfg(feature = "anyhow")]
+pub use anyhow_error::InlineAnyhowChain;
 #[cfg(feature = "nested-values")]
 pub use erased_serde;
 #[cfg(feature = "nested-values")]
 mod nested_values;
 #[cfg(feature = "nested-values")]
 pub use nested_values::*;
+#[cfg(feature = "nested-values")]
+mod object;
+#[cfg(feature = "nested-values")]
+pub use object::ObjectErrorChain;
+#[cfg(feature = "nested-values")]
+pub use object::OwnedObjectErrorChain;
+
+/// Implementation details used by the derive macros.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::macros::MacroChain;
+    pub use crate::type_names::Probe;
+    pub use crate::type_names::PushTypeName;
+    pub use crate::type_names::PushTypeNames;
+
+    /// Required of every type deriving this crate's macros, so that a type
+    /// that isn't an error is reported as such, rather than by the
+    /// implementations that rely on it being one.
+    #[diagnostic::on_unimplemented(
+        message = "`{Self}` must implement `std::error::Error` to derive \
+                   `slog_error_chain`'s macros",
+        label = "not an error",
+        note = "implement `std::error::Error` for `{Self}`, e.g., via \
+                `#[derive(thiserror::Error)]`"
+    )]
+    pub trait DeriveError: std::error::Error {}
+
+    impl<E: std::error::Error + ?Sized> DeriveError for E {}
+}
 
 #[cfg(all(feature = "derive", feature = "nested-values"))]
 pub use slog_error_chain_derive::SlogArrayError;
 #[cfg(feature = "derive")]
+pub use slog_error_chain_derive::SlogError;
+#[cfg(feature = "derive")]
 pub use slog_error_chain_derive::SlogInlineError;
+#[cfg(all(feature = "derive", feature = "nested-values"))]
+pub use slog_error_chain_derive::SlogObjectError;
 
 /// Adapter for [`Error`]s that provides both [`std::fmt::Display`] and
 /// [`slog::Value`] implementations that print the full chain of error sources,
 /// separated by `: `.
-pub struct InlineErrorChain<'a>(&'a dyn Error);
+///
+/// If an error's `source()` chain contains a cycle, printing stops at the first
+/// repeated error and `... (cycle detected)` is printed in its place.
+///
+/// Formatting with the alternate flag (`{:#}`) prints a multi-line report
+/// instead; see [`ReportErrorChain`].
+///
+/// The rendering can be customized by chaining options onto the adapter, e.g.,
+/// `InlineErrorChain::new(&err).separator(" <- ").max_depth(8)`.
+pub struct InlineErrorChain<'a> {
+    err: &'a dyn Error,
+    // `err`, if its type is known to be `'static`; see `from_static()`.
+    err_static: Option<&'a (dyn Error + 'static)>,
+    fields: Option<&'a dyn ErrorFields>,
+    format: ChainFormat,
+}
 
 impl<'a> InlineErrorChain<'a> {
     /// Construct a new `InlineErrorChain` from an error.
     pub fn new(err: &'a dyn Error) -> Self {
-        Self(err)
+        Self::with_format(err, None, ChainFormat::default())
+    }
+
+    /// Construct a new `InlineErrorChain` from a `'static` error, which (unlike
+    /// an error of unknown lifetime) can be downcast: if it is an
+    /// [`std::io::Error`], its errno name is printed when enabled by
+    /// [`errno_names`](InlineErrorChain::errno_names), and if its type has
+    /// been registered with [`register_error_fields`], its [`ErrorFields`] are
+    /// also logged by the `slog::KV` implementation.
+    pub fn from_static(err: &'a (dyn Error + 'static)) -> Self {
+        let fields = fields::fields_of(err);
+        let format = ChainFormat::default();
+        Self { fields, ..Self::with_format(err, Some(err), format) }
+    }
+
+    /// Construct a new `InlineErrorChain` from an error whose own
+    /// [`ErrorFields`] are also logged by the `slog::KV` implementation, as
+    /// `{key}.0.{field}`. The fields of its causes are logged if their types
+    /// have been registered with [`register_error_fields`].
+    pub fn with_fields<E: Error + ErrorFields>(err: &'a E) -> Self {
+        Self { fields: Some(err), ..Self::new(err) }
+    }
+
+    chain_format_options! {
+        with_key,
+        emit_root_cause,
+        separator,
+        max_depth,
+        prefix_index,
+        suppress_double_speak,
+        max_layer_len,
+        max_len,
+        escape,
+        errno_names,
+    }
+
+    /// Construct an `InlineErrorChain` for `err`, which is also given as
+    /// `err_static` if its type is known to be `'static`.
+    pub(crate) fn with_format(
+        err: &'a dyn Error,
+        err_static: Option<&'a (dyn Error + 'static)>,
+        format: ChainFormat,
+    ) -> Self {
+        Self { err, err_static, fields: None, format }
     }
 }
 
 impl KV for InlineErrorChain<'_> {
     // If slog is built with the `dynamic-keys` feature, we have to pass a `Key`
-    // to `emit_arguments`, hence `"error".into()`. However, if slog is built
+    // to `emit_arguments`, hence `key.into()`. However, if slog is built
     // without `dynamic-keys`, `emit_arguments` expects a `&str`.
-    // `"error".into()` is still valid, but clippy warns about the useless
+    // `key.into()` is still valid, but clippy warns about the useless
     // `.into()` call. Suppress that warning so we can use the same code
     // regardless of the slog feature.
     #[allow(clippy::useless_conversion)]
     fn serialize(
         &self,
-        _record: &slog::Record,
+        record: &slog::Record,
         serializer: &mut dyn slog::Serializer,
     ) -> slog::Result {
-        serializer.emit_arguments("error".into(), &format_args!("{self}"))
+        let format = self.format.for_slog();
+        let chain = Self::with_format(self.err, self.err_static, format);
+        serializer
+            .emit_arguments(format.key.into(), &format_args!("{chain}"))?;
+        format.emit_root_cause_of(self.err, serializer)?;
+        format.emit_fields_of(self.err, self.fields, record, serializer)
     }
 }
 
@@ -58,19 +188,54 @@ impl Value for InlineErrorChain<'_> {
         key: slog::Key,
         serializer: &mut dyn slog::Serializer,
     ) -> slog::Result {
-        serializer.emit_arguments(key, &format_args!("{self}"))
+        let format = self.format.for_slog();
+        let chain = Self::with_format(self.err, self.err_static, format);
+        serializer.emit_arguments(key, &format_args!("{chain}"))
     }
 }
 
 impl fmt::Display for InlineErrorChain<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)?;
-        let mut cause = self.0.source();
-        while let Some(source) = cause {
-            write!(f, ": {source}")?;
-            cause = source.source();
-        }
-        Ok(())
+        let layers = Chain::with_max_depth(self.err, self.format.max_depth)
+            .with_static_root(self.err_static);
+        self.format.write_layers(f, layers)
+    }
+}
+
+/// Adapter for [`Error`]s that provides a [`std::fmt::Display`] implementation
+/// that prints a multi-line report of the full chain of error sources, suitable
+/// for CLI output or panic messages:
+///
+/// ```text
+/// an I/O error occurred trying to open /some/path
+///
+/// Caused by:
+///     0: file not found
+/// ```
+///
+/// This is equivalent to formatting an [`InlineErrorChain`] with the alternate
+/// flag (`{:#}`). A `ReportErrorChain` can also be constructed from an
+/// `InlineErrorChain` to carry over its options (e.g., `max_depth`); options
+/// that only affect the single-line format, such as the separator, are
+/// ignored.
+pub struct ReportErrorChain<'a>(InlineErrorChain<'a>);
+
+impl<'a> ReportErrorChain<'a> {
+    /// Construct a new `ReportErrorChain` from an error.
+    pub fn new(err: &'a dyn Error) -> Self {
+        Self(InlineErrorChain::new(err))
+    }
+}
+
+impl<'a> From<InlineErrorChain<'a>> for ReportErrorChain<'a> {
+    fn from(chain: InlineErrorChain<'a>) -> Self {
+        Self(chain)
+    }
+}
+
+impl fmt::Display for ReportErrorChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.0)
     }
 }
 
@@ -79,6 +244,7 @@ mod tests {
     use std::io;
 
     use super::*;
+    use crate::chain::tests::{CyclicError, DeepError};
 
     #[derive(Debug, thiserror::Error)]
     pub(crate) enum ErrorA {
@@ -109,4 +275,460 @@ mod tests {
             "error b: error a: test error"
         );
     }
+
+    #[test]
+    fn inline_error_chain_options() {
+        let err = ErrorB::B(ErrorA::A(io::Error::other("test error")));
+
+        assert_eq!(
+            InlineErrorChain::new(&err).separator(" <- ").to_string(),
+            "error b <- error a <- test error"
+        );
+        assert_eq!(
+            InlineErrorChain::new(&err).max_depth(2).to_string(),
+            "error b: error a: ... (1 more cause)"
+        );
+        assert_eq!(
+            InlineErrorChain::new(&err).max_depth(0).to_string(),
+            "error b: ... (2 more causes)"
+        );
+        assert_eq!(
+            InlineErrorChain::new(&err).prefix_index(true).to_string(),
+            "[0] error b: [1] error a: [2] test error"
+        );
+        assert_eq!(
+            InlineErrorChain::new(&err)
+                .separator(" <- ")
+                .max_depth(2)
+                .prefix_index(true)
+                .to_string(),
+            "[0] error b <- [1] error a <- ... (1 more cause)"
+        );
+    }
+
+    #[test]
+    fn inline_error_chain_truncation() {
+        let err =
+            CyclicError { inner: Some(Box::new(CyclicError { inner: None })) };
+        assert_eq!(
+            InlineErrorChain::new(&err).to_string(),
+            "cyclic: cyclic: ... (cycle detected)"
+        );
+
+        // By default, all but pathologically deep chains are printed in full.
+        let err = DeepError(100);
+        let chain = InlineErrorChain::new(&err).to_string();
+        assert!(chain.starts_with("depth 100: depth 99: "), "{chain}");
+        assert!(chain.ends_with(": depth 2: depth 1"), "{chain}");
+        let chain = InlineErrorChain::new(&err).max_depth(32).to_string();
+        assert!(chain.ends_with(": depth 69: ... (68 more causes)"), "{chain}");
+
+        // Only so many omitted causes are counted.
+        let err = DeepError(20_000);
+        let chain = InlineErrorChain::new(&err).max_depth(32).to_string();
+        assert!(chain.ends_with(": ... (992+ more causes)"), "{chain}");
+        let chain = InlineErrorChain::new(&err).to_string();
+        assert!(chain.ends_with(": depth 18977: ... (1+ more causes)"));
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    pub(crate) enum EmbeddingError {
+        #[error("opening file: {0}")]
+        Opening(#[source] io::Error),
+        #[error("{0}")]
+        Same(#[source] io::Error),
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("wrapped: {0}")]
+    pub(crate) struct EmbeddingWrapper(#[source] pub(crate) EmbeddingError);
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("whole chain: {}", InlineErrorChain::new(.0))]
+    struct EmbeddingChain(#[source] ErrorA);
+
+    #[test]
+    fn inline_error_chain_double_speak() {
+        let err = EmbeddingError::Opening(io::Error::other("not found"));
+        assert_eq!(
+            InlineErrorChain::new(&err).to_string(),
+            "opening file: not found: not found"
+        );
+        assert_eq!(
+            InlineErrorChain::new(&err).suppress_double_speak(true).to_string(),
+            "opening file: not found"
+        );
+
+        // Embedding compounds as layers are added; each layer is cleaned.
+        let err = EmbeddingWrapper(err);
+        assert_eq!(
+            InlineErrorChain::new(&err).to_string(),
+            "wrapped: opening file: not found: opening file: not found: \
+             not found"
+        );
+        assert_eq!(
+            InlineErrorChain::new(&err).suppress_double_speak(true).to_string(),
+            "wrapped: opening file: not found"
+        );
+
+        // A layer that only repeats its source is dropped.
+        let err = EmbeddingError::Same(io::Error::other("not found"));
+        assert_eq!(
+            InlineErrorChain::new(&err)
+                .suppress_double_speak(true)
+                .prefix_index(true)
+                .to_string(),
+            "[0] not found"
+        );
+
+        // A layer that embeds the entire remainder of the chain.
+        let err = EmbeddingChain(ErrorA::A(io::Error::other("test error")));
+        assert_eq!(
+            InlineErrorChain::new(&err).suppress_double_speak(true).to_string(),
+            "whole chain: error a: test error"
+        );
+
+        // Coincidental suffixes that aren't separated by a colon are kept.
+        let err = ErrorA::A(io::Error::other("a"));
+        assert_eq!(
+            InlineErrorChain::new(&err).suppress_double_speak(true).to_string(),
+            "error a: a"
+        );
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("multi-line\nerror\n\nwith a blank line")]
+    struct MultiLineError(#[source] ErrorA);
+
+    #[test]
+    fn report_error_chain_formatting() {
+        let err = io::Error::other("test error");
+        assert_eq!(ReportErrorChain::new(&err).to_string(), "test error");
+
+        let err = ErrorB::B(ErrorA::A(err));
+        let expected = "\
+error b
+
+Caused by:
+    0: error a
+    1: test error";
+        assert_eq!(ReportErrorChain::new(&err).to_string(), expected);
+        assert_eq!(format!("{:#}", InlineErrorChain::new(&err)), expected);
+
+        // Single-line options are ignored, but depth limits are kept.
+        let chain = InlineErrorChain::new(&err).separator(" <- ").max_depth(2);
+        assert_eq!(
+            ReportErrorChain::from(chain).to_string(),
+            "\
+error b
+
+Caused by:
+    0: error a
+    ... (1 more cause)"
+        );
+
+        let err = MultiLineError(ErrorA::A(io::Error::other("line 1\nline 2")));
+        assert_eq!(
+            ReportErrorChain::new(&err).to_string(),
+            "\
+multi-line
+error
+
+with a blank line
+
+Caused by:
+    0: error a
+    1: line 1
+       line 2"
+        );
+    }
+
+    #[test]
+    fn inline_error_chain_length_limits() {
+        let err = ErrorB::B(ErrorA::A(io::Error::other("ünïcödé error")));
+
+        // Each message is cut on a char boundary, leaving room for the `…`.
+        assert_eq!(
+            InlineErrorChain::new(&err).max_layer_len(6).to_string(),
+            "err…: err…: ün…"
+        );
+        assert_eq!(
+            InlineErrorChain::new(&err).max_layer_len(7).to_string(),
+            "error b: error a: ün…"
+        );
+        // Messages that fit exactly are not marked.
+        assert_eq!(
+            InlineErrorChain::new(&err).max_layer_len(18).to_string(),
+            "error b: error a: ünïcödé error"
+        );
+
+        assert_eq!(
+            InlineErrorChain::new(&err).max_len(20).to_string(),
+            "error b: error a:…"
+        );
+        assert_eq!(
+            InlineErrorChain::new(&err).max_len(23).to_string(),
+            "error b: error a: ü…"
+        );
+        let full = InlineErrorChain::new(&err).to_string();
+        assert_eq!(
+            InlineErrorChain::new(&err).max_len(full.len()).to_string(),
+            full
+        );
+        assert_eq!(InlineErrorChain::new(&err).max_len(2).to_string(), "er");
+
+        // Precision is a shorthand for `max_len`; the smaller limit wins.
+        assert_eq!(format!("{:.10}", InlineErrorChain::new(&err)), "error b…");
+        assert_eq!(
+            format!("{:.20}", InlineErrorChain::new(&err).max_len(10)),
+            "error b…"
+        );
+
+        // Limits also apply to reports.
+        assert_eq!(
+            format!("{:#}", InlineErrorChain::new(&err).max_layer_len(5)),
+            "er…\n\nCaused by:\n    0: er…\n    1: ü…"
+        );
+    }
+
+    /// Serializer that records each emitted key and value as a string.
+    #[derive(Default, Debug)]
+    pub(crate) struct KeyValueSerializer(pub(crate) Vec<(String, String)>);
+
+    impl slog::Serializer for KeyValueSerializer {
+        fn emit_arguments(
+            &mut self,
+            key: slog::Key,
+            val: &fmt::Arguments,
+        ) -> slog::Result {
+            self.0.push((key.to_string(), val.to_string()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn inline_error_chain_escaping() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record =
+            slog::record!(slog::Level::Info, "dummy", &dummy_args, slog::b!());
+
+        let err = ErrorA::A(io::Error::other("tab\tline\r\n\x1b[1mbold\u{7f}"));
+        let escaped = r"error a: tab\tline\r\n\u{1b}[1mbold\u{7f}";
+
+        let chain = InlineErrorChain::new(&err);
+        assert_eq!(
+            chain.to_string(),
+            "error a: tab\tline\r\n\x1b[1mbold\u{7f}"
+        );
+        assert_eq!(chain.escape(true).to_string(), escaped);
+
+        let mut out = KeyValueSerializer::default();
+        KV::serialize(&InlineErrorChain::new(&err), &dummy_record, &mut out)
+            .unwrap();
+        Value::serialize(
+            &InlineErrorChain::new(&err),
+            &dummy_record,
+            "my-key",
+            &mut out,
+        )
+        .unwrap();
+        Value::serialize(
+            &InlineErrorChain::new(&err).escape(false),
+            &dummy_record,
+            "raw",
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            out.0,
+            [
+                ("error".to_string(), escaped.to_string()),
+                ("my-key".to_string(), escaped.to_string()),
+                (
+                    "raw".to_string(),
+                    "error a: tab\tline\r\n\x1b[1mbold\u{7f}".to_string()
+                ),
+            ]
+        );
+
+        // Escaped text counts towards length limits.
+        assert_eq!(
+            InlineErrorChain::new(&err)
+                .escape(true)
+                .max_layer_len(10)
+                .to_string(),
+            r"error a: tab\tli…"
+        );
+    }
+
+    #[test]
+    fn inline_error_chain_key() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record =
+            slog::record!(slog::Level::Info, "dummy", &dummy_args, slog::b!());
+
+        let err = ErrorA::A(io::Error::other("test error"));
+        let mut out = KeyValueSerializer::default();
+        KV::serialize(&InlineErrorChain::new(&err), &dummy_record, &mut out)
+            .unwrap();
+        KV::serialize(
+            &InlineErrorChain::new(&err).with_key("err"),
+            &dummy_record,
+            &mut out,
+        )
+        .unwrap();
+        // An explicit key takes precedence.
+        Value::serialize(
+            &InlineErrorChain::new(&err).with_key("err"),
+            &dummy_record,
+            "explicit",
+            &mut out,
+        )
+        .unwrap();
+        let keys = out.0.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, ["error", "err", "explicit"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn inline_error_chain_errno_names() {
+        let err =
+            ErrorB::B(ErrorA::A(io::Error::from_raw_os_error(libc::ENOENT)));
+        let message = io::Error::from_raw_os_error(libc::ENOENT).to_string();
+        assert_eq!(
+            InlineErrorChain::new(&err).to_string(),
+            format!("error b: error a: {message}")
+        );
+        assert_eq!(
+            InlineErrorChain::new(&err).errno_names(true).to_string(),
+            format!(
+                "error b: error a: {}, ENOENT)",
+                message.strip_suffix(')').unwrap()
+            )
+        );
+
+        // Only layers that are `io::Error`s with an OS error code are
+        // annotated, whatever their messages look like.
+        let err = ErrorA::A(io::Error::other("not found (os error 2)"));
+        assert_eq!(
+            InlineErrorChain::new(&err).errno_names(true).to_string(),
+            "error a: not found (os error 2)"
+        );
+        // That includes layers embedding an `io::Error`'s message.
+        let io_err = io::Error::from_raw_os_error(libc::ENOENT);
+        let err = EmbeddingError::Opening(io_err);
+        assert_eq!(
+            InlineErrorChain::new(&err).errno_names(true).to_string(),
+            format!(
+                "opening file: {message}: {}, ENOENT)",
+                message.strip_suffix(')').unwrap()
+            )
+        );
+
+        // The topmost error is only recognized if it is known to be
+        // `'static`.
+        let err = io::Error::from_raw_os_error(libc::ENOENT);
+        assert_eq!(
+            InlineErrorChain::new(&err).errno_names(true).to_string(),
+            message
+        );
+        assert_eq!(
+            InlineErrorChain::from_static(&err).errno_names(true).to_string(),
+            format!("{}, ENOENT)", message.strip_suffix(')').unwrap())
+        );
+    }
+
+    #[test]
+    fn inline_error_chain_fields() {
+        use crate::fields::tests::OpeningFile;
+
+        let dummy_args = format_args!("dummy");
+        let dummy_record =
+            slog::record!(slog::Level::Info, "dummy", &dummy_args, slog::b!());
+
+        let err = OpeningFile { path: "/a", err: io::Error::other("denied") };
+        let mut out = KeyValueSerializer::default();
+        KV::serialize(
+            &InlineErrorChain::with_fields(&err).with_key("err"),
+            &dummy_record,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            out.0,
+            [
+                ("err".to_string(), "opening /a: denied".to_string()),
+                ("err.0.path".to_string(), "/a".to_string()),
+                ("err.0.len".to_string(), "2".to_string()),
+            ]
+        );
+
+        // Fields aren't part of the value itself.
+        let mut out = KeyValueSerializer::default();
+        Value::serialize(
+            &InlineErrorChain::with_fields(&err),
+            &dummy_record,
+            "key",
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out.0.len(), 1);
+    }
+
+    #[test]
+    fn inline_error_chain_root_cause() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record =
+            slog::record!(slog::Level::Info, "dummy", &dummy_args, slog::b!());
+
+        let err = ErrorB::B(ErrorA::A(io::Error::other("line\nbreak")));
+        let mut out = KeyValueSerializer::default();
+        KV::serialize(
+            &InlineErrorChain::new(&err).with_key("err").emit_root_cause(true),
+            &dummy_record,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            out.0,
+            [
+                (
+                    "err".to_string(),
+                    r"error b: error a: line\nbreak".to_string()
+                ),
+                ("err.root".to_string(), r"line\nbreak".to_string()),
+                ("err.depth".to_string(), "3".to_string()),
+            ]
+        );
+
+        // The root cause and depth reflect the entire chain, even if it isn't
+        // all printed.
+        let err = DeepError(40);
+        let mut out = KeyValueSerializer::default();
+        KV::serialize(
+            &InlineErrorChain::new(&err).max_depth(2).emit_root_cause(true),
+            &dummy_record,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            out.0[1..],
+            [
+                ("error.root".to_string(), "depth 1".to_string()),
+                ("error.depth".to_string(), "40".to_string()),
+            ]
+        );
+
+        // A cycle ends the chain at the last distinct error.
+        let err =
+            CyclicError { inner: Some(Box::new(CyclicError { inner: None })) };
+        let mut out = KeyValueSerializer::default();
+        KV::serialize(
+            &InlineErrorChain::new(&err).emit_root_cause(true),
+            &dummy_record,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out.0[2], ("error.depth".to_string(), "2".to_string()));
+    }
 }
@@ -0,0 +1,180 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Logging macros that attach an error chain to the record, so it can't be
+//! accidentally logged via its `Display` impl (which drops its causes).
+
+/// The adapter through which the logging macros log error chains.
+#[cfg(not(feature = "nested-values"))]
+pub type MacroChain<'a> = crate::InlineErrorChain<'a>;
+#[cfg(feature = "nested-values")]
+pub type MacroChain<'a> = crate::ArrayErrorChain<'a>;
+
+/// Log a message at the given level along with an error chain.
+///
+/// This takes the logger, the `slog::Level` (which need not be constant), a
+/// reference to the error, and then a message and key/value pairs as accepted
+/// by `slog::info!` et al. The chain is logged via the `slog::KV`
+/// implementation of an `ArrayErrorChain` if the `nested-values` feature is
+/// enabled, or of an [`InlineErrorChain`](crate::InlineErrorChain) otherwise
+/// (i.e., under the key `error`):
+///
+/// ```
+/// # use slog_error_chain::log_chain;
+/// # let log = slog::Logger::root(slog::Discard, slog::o!());
+/// # let err = std::io::Error::other("oops");
+/// let path = "/etc/config";
+/// log_chain!(log, slog::Level::Warning, &err, "reading {path}"; "retries" => 3);
+/// ```
+///
+/// [`crit!`](crate::crit), [`error!`](crate::error), [`warn!`](crate::warn),
+/// [`info!`](crate::info), [`debug!`](crate::debug), and
+/// [`trace!`](crate::trace) log at a fixed level, e.g.,
+/// `slog_error_chain::error!(log, &err, "reading {path}")`.
+#[macro_export]
+macro_rules! log_chain {
+    // Split the arguments at `;` into the message and key/value pairs, then
+    // log them via `slog::$level!`.
+    (@split $level:ident, $log:expr, $chain:ident, [$($msg:tt)*] ; $($kv:tt)*) => {
+        $crate::log_chain!(@log $level, $log, $chain, [$($msg)*] [$($kv)*])
+    };
+    (@split $level:ident, $log:expr, $chain:ident, [$($msg:tt)*]) => {
+        $crate::log_chain!(@log $level, $log, $chain, [$($msg)*] [])
+    };
+    (@split $level:ident, $log:expr, $chain:ident, [$($msg:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::log_chain!(@split $level, $log, $chain, [$($msg)* $next] $($rest)*)
+    };
+    (@log $level:ident, $log:expr, $chain:ident, [$($msg:tt)*] []) => {
+        ::slog::$level!($log, $($msg)*; $chain)
+    };
+    (@log $level:ident, $log:expr, $chain:ident, [$($msg:tt)*] [$($kv:tt)+]) => {
+        ::slog::$level!($log, $($msg)*; $chain, $($kv)+)
+    };
+    ($log:expr, $level:expr, $err:expr, $($args:tt)+) => {{
+        let chain = $crate::__private::MacroChain::new($err);
+        // `slog` requires a constant level, so pick among them.
+        match $level {
+            ::slog::Level::Critical => {
+                $crate::log_chain!(@split crit, $log, chain, [] $($args)+)
+            }
+            ::slog::Level::Error => {
+                $crate::log_chain!(@split error, $log, chain, [] $($args)+)
+            }
+            ::slog::Level::Warning => {
+                $crate::log_chain!(@split warn, $log, chain, [] $($args)+)
+            }
+            ::slog::Level::Info => {
+                $crate::log_chain!(@split info, $log, chain, [] $($args)+)
+            }
+            ::slog::Level::Debug => {
+                $crate::log_chain!(@split debug, $log, chain, [] $($args)+)
+            }
+            ::slog::Level::Trace => {
+                $crate::log_chain!(@split trace, $log, chain, [] $($args)+)
+            }
+        }
+    }};
+}
+
+/// Log a message at critical level along with an error chain, as
+/// [`log_chain!`](crate::log_chain).
+#[macro_export]
+macro_rules! crit {
+    ($log:expr, $err:expr, $($args:tt)+) => {{
+        let chain = $crate::__private::MacroChain::new($err);
+        $crate::log_chain!(@split crit, $log, chain, [] $($args)+)
+    }};
+}
+
+/// Log a message at error level along with an error chain, as
+/// [`log_chain!`](crate::log_chain).
+#[macro_export]
+macro_rules! error {
+    ($log:expr, $err:expr, $($args:tt)+) => {{
+        let chain = $crate::__private::MacroChain::new($err);
+        $crate::log_chain!(@split error, $log, chain, [] $($args)+)
+    }};
+}
+
+/// Log a message at warning level along with an error chain, as
+/// [`log_chain!`](crate::log_chain).
+#[macro_export]
+macro_rules! warn {
+    ($log:expr, $err:expr, $($args:tt)+) => {{
+        let chain = $crate::__private::MacroChain::new($err);
+        $crate::log_chain!(@split warn, $log, chain, [] $($args)+)
+    }};
+}
+
+/// Log a message at info level along with an error chain, as
+/// [`log_chain!`](crate::log_chain).
+#[macro_export]
+macro_rules! info {
+    ($log:expr, $err:expr, $($args:tt)+) => {{
+        let chain = $crate::__private::MacroChain::new($err);
+        $crate::log_chain!(@split info, $log, chain, [] $($args)+)
+    }};
+}
+
+/// Log a message at debug level along with an error chain, as
+/// [`log_chain!`](crate::log_chain).
+#[macro_export]
+macro_rules! debug {
+    ($log:expr, $err:expr, $($args:tt)+) => {{
+        let chain = $crate::__private::MacroChain::new($err);
+        $crate::log_chain!(@split debug, $log, chain, [] $($args)+)
+    }};
+}
+
+/// Log a message at trace level along with an error chain, as
+/// [`log_chain!`](crate::log_chain).
+#[macro_export]
+macro_rules! trace {
+    ($log:expr, $err:expr, $($args:tt)+) => {{
+        let chain = $crate::__private::MacroChain::new($err);
+        $crate::log_chain!(@split trace, $log, chain, [] $($args)+)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::level::tests::Logged;
+    use crate::level::tests::Records;
+    use crate::tests::ErrorA;
+    use crate::tests::ErrorB;
+    use slog::Level;
+    use std::io;
+
+    #[test]
+    fn log_chain_levels() {
+        let records = Records::default();
+        let log = records.logger();
+        let err = ErrorB::B(ErrorA::A(io::Error::other("test error")));
+
+        let level = Level::Debug;
+        log_chain!(log, level, &err, "plain");
+        log_chain!(log, Level::Critical, &err, "with {}", "args"; "a" => 1);
+        crate::error!(log, &err, "fixed"; "a" => 1, "b" => 2,);
+        let n = 3;
+        crate::warn!(log, &err, "fixed {n}");
+
+        let chain = "error=error b: error a: test error";
+        let logged = |level, msg: &str, kvs: &[&str]| -> Logged {
+            (
+                level,
+                msg.to_string(),
+                kvs.iter().map(|kv| kv.to_string()).collect(),
+            )
+        };
+        assert_eq!(
+            records.take(),
+            [
+                logged(Level::Debug, "plain", &[chain]),
+                logged(Level::Critical, "with args", &["a=1", chain]),
+                logged(Level::Error, "fixed", &["b=2", "a=1", chain]),
+                logged(Level::Warning, "fixed 3", &[chain]),
+            ]
+        );
+    }
+}
@@ -5,9 +5,18 @@
 //! [`ArrayErrorChain`] supports logging error chains as arrays of strings, one
 //! element per cause, via [`slog::SerdeValue`] for loggers that support
 //! structured values (aka `nested-values`), such as `slog-json`.
-
+//! [`LinkedErrorChain`] does the same, but as nested objects.
+
+use crate::chain;
+use crate::chain::Chain;
+use crate::chain::Truncation;
+use crate::fields;
+use crate::fields::ErrorFields;
+use crate::format::chain_format_options;
+use crate::format::ChainFormat;
+use crate::format::Layers;
+use crate::io_error::IoDetails;
 use crate::InlineErrorChain;
-use serde::ser::SerializeSeq;
 use serde::Serialize;
 use slog::SerdeValue;
 use slog::Value;
@@ -15,40 +24,182 @@ use slog::KV;
 use std::error::Error;
 use std::fmt;
 
-/// An owned, `'static` version of an [`ArrayErrorChain`].
+/// An owned, `'static` version of an [`ArrayErrorChain`] or (if `linked` is
+/// set) a [`LinkedErrorChain`].
+///
+/// Like [`InlineErrorChain`], formatting an `OwnedErrorChain` with the
+/// alternate flag (`{:#}`) prints a multi-line report of the chain.
 ///
 /// `OwnedErrorChain` is relatively expensive to construct, as it always
 /// allocates a `String` for the initial error and additionally allocates a
 /// `Vec<String>` for any causes in the error's chain. This type exists
-/// primarily to allow [`ArrayErrorChain`] to implement [`slog::SerdeValue`],
+/// primarily to allow [`ArrayErrorChain`] and [`LinkedErrorChain`] to
+/// implement [`slog::SerdeValue`],
 /// which requires the ability to convert to an owned value (e.g., to offload to
 /// another thread for serialization, such as when `slog-async` is used).
 #[derive(Debug, Clone)]
 pub struct OwnedErrorChain {
     first: String,
     rest: Vec<String>,
+    truncation: Option<Truncation>,
+    // The innermost cause and the depth of the chain, if the cause is beyond
+    // the captured layers.
+    root: Option<(String, usize)>,
+    // The details of each captured layer that is an `io::Error`.
+    io: Vec<Option<IoDetails>>,
+    pub(crate) format: ChainFormat,
 }
 
 impl OwnedErrorChain {
     /// Construct a new `OwnedErrorChain` from an error.
     pub fn new(err: &dyn Error) -> Self {
-        let mut causes = vec![];
-        let mut source = err.source();
-        while let Some(cause) = source {
-            causes.push(cause.to_string());
-            source = cause.source();
+        Self::with_format(err, None, ChainFormat::default())
+    }
+
+    /// Construct a new `OwnedErrorChain` from a `'static` error, capturing its
+    /// details if it is an [`std::io::Error`]. See
+    /// [`LinkedErrorChain::from_static`].
+    pub fn from_static(err: &(dyn Error + 'static)) -> Self {
+        Self::with_format(err, Some(err), ChainFormat::default())
+    }
+
+    chain_format_options! {
+        with_key,
+        emit_root_cause,
+        separator,
+        ///
+        /// The chain is captured when the `OwnedErrorChain` is constructed,
+        /// which walks at most 1024 errors; this can only reduce that depth.
+        max_depth,
+        prefix_index,
+        suppress_double_speak,
+        max_layer_len,
+        ///
+        /// When serialized, this limits the total length of all elements; no
+        /// further elements are serialized once it has been reached.
+        max_len,
+        escape,
+        errno_names,
+    }
+
+    /// If true, serialize the chain as nested objects rather than an array
+    /// (default: false). See [`LinkedErrorChain`].
+    pub fn linked(mut self, linked: bool) -> Self {
+        self.format.linked = linked;
+        self
+    }
+
+    /// Capture the chain of `err`, which is also given as `err_static` if its
+    /// type is known to be `'static`.
+    pub(crate) fn with_format(
+        err: &dyn Error,
+        err_static: Option<&(dyn Error + 'static)>,
+        format: ChainFormat,
+    ) -> Self {
+        let mut chain = Chain::with_max_depth(err, format.max_depth)
+            .with_static_root(err_static);
+        let first = chain.next().unwrap_or(err).to_string();
+        let mut io = vec![chain.last_static().and_then(IoDetails::of)];
+        let mut rest = Vec::new();
+        while let Some(cause) = chain.next() {
+            rest.push(cause.to_string());
+            io.push(chain.last_static().and_then(IoDetails::of));
+        }
+        let truncation = chain.truncation();
+        let root = match truncation {
+            Some(Truncation::Depth(_) | Truncation::DepthAtLeast(_)) => {
+                let (root, depth) = chain::root_and_depth(err);
+                Some((root.to_string(), depth))
+            }
+            Some(Truncation::Cycle) | None => None,
+        };
+        Self { first, rest, truncation, root, io, format }
+    }
+
+    /// If enabled, log the message of the innermost cause and the number of
+    /// errors in the chain, including any beyond the captured layers.
+    pub(crate) fn emit_root_cause_to(
+        &self,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        if !self.format.root_cause {
+            return Ok(());
+        }
+        let (root, depth) = match &self.root {
+            Some((root, depth)) => (root, *depth),
+            None => {
+                (self.rest.last().unwrap_or(&self.first), 1 + self.rest.len())
+            }
+        };
+        self.format.for_slog().emit_root_cause(root, depth, serializer)
+    }
+
+    pub(crate) fn layers(&self) -> OwnedLayers<'_> {
+        OwnedLayers { chain: self, index: 0 }
+    }
+}
+
+/// Iterator over the layers of an [`OwnedErrorChain`], applying its
+/// `max_depth`.
+pub(crate) struct OwnedLayers<'a> {
+    chain: &'a OwnedErrorChain,
+    index: usize,
+}
+
+impl<'a> Iterator for OwnedLayers<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.chain.format.max_depth.max(1) {
+            return None;
+        }
+        let layer = match self.index {
+            0 => &self.chain.first,
+            i => self.chain.rest.get(i - 1)?,
+        };
+        self.index += 1;
+        Some(layer)
+    }
+}
+
+impl Layers for OwnedLayers<'_> {
+    fn truncation(&self) -> Option<Truncation> {
+        let total = 1 + self.chain.rest.len();
+        let shown = self.chain.format.max_depth.max(1);
+        if total <= shown {
+            return self.chain.truncation;
+        }
+        let omitted = total - shown;
+        match self.chain.truncation {
+            Some(Truncation::Depth(n)) => Some(Truncation::Depth(omitted + n)),
+            Some(Truncation::DepthAtLeast(n)) => {
+                Some(Truncation::DepthAtLeast(omitted + n))
+            }
+            Some(Truncation::Cycle) | None => Some(Truncation::Depth(omitted)),
         }
-        Self { first: err.to_string(), rest: causes }
+    }
+
+    fn io_details(&self) -> Option<IoDetails> {
+        let index = self.index.checked_sub(1)?;
+        self.chain.io.get(index).copied().flatten()
     }
 }
 
 impl fmt::Display for OwnedErrorChain {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.first)?;
-        for source in &self.rest {
-            write!(f, ": {source}")?;
-        }
-        Ok(())
+        self.format.write_layers(f, self.layers())
+    }
+}
+
+/// Displays an [`OwnedErrorChain`] with options other than its own.
+struct OwnedWithFormat<'a> {
+    chain: &'a OwnedErrorChain,
+    format: ChainFormat,
+}
+
+impl fmt::Display for OwnedWithFormat<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.format.write_layers(f, self.chain.layers())
     }
 }
 
@@ -57,12 +208,7 @@ impl Serialize for OwnedErrorChain {
     where
         S: serde::Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(1 + self.rest.len()))?;
-        seq.serialize_element(self.first.as_str())?;
-        for s in &self.rest {
-            seq.serialize_element(s.as_str())?;
-        }
-        seq.end()
+        self.format.serialize_layers(serializer, self.layers())
     }
 }
 
@@ -73,7 +219,8 @@ impl KV for OwnedErrorChain {
         _record: &slog::Record,
         serializer: &mut dyn slog::Serializer,
     ) -> slog::Result {
-        serializer.emit_serde("error".into(), self)
+        serializer.emit_serde(self.format.key.into(), self)?;
+        self.emit_root_cause_to(serializer)
     }
 }
 
@@ -102,7 +249,9 @@ impl SerdeValue for OwnedErrorChain {
         key: slog::Key,
         serializer: &mut dyn slog::Serializer,
     ) -> slog::Result<()> {
-        serializer.emit_arguments(key, &format_args!("{self}"))
+        let chain =
+            OwnedWithFormat { chain: self, format: self.format.for_slog() };
+        serializer.emit_arguments(key, &format_args!("{chain}"))
     }
 }
 
@@ -112,19 +261,62 @@ impl SerdeValue for OwnedErrorChain {
 /// `ArrayErrorChain`'s `Display` implementation and its fallback `SerdeValue`
 /// format when using a logger that does not support nested values matches the
 /// behavior of [`InlineErrorChain`]: the chain of errors is printed as a single
-/// string with the causes separated by `: `.
-pub struct ArrayErrorChain<'a>(&'a dyn Error);
+/// string with the causes separated by `: `. The same rendering options as
+/// `InlineErrorChain` are available to customize that string.
+pub struct ArrayErrorChain<'a> {
+    err: &'a dyn Error,
+    // `err`, if its type is known to be `'static`; see `from_static()`.
+    err_static: Option<&'a (dyn Error + 'static)>,
+    fields: Option<&'a dyn ErrorFields>,
+    format: ChainFormat,
+}
 
 impl<'a> ArrayErrorChain<'a> {
     /// Construct a new `ArrayErrorChain` from an error.
     pub fn new(err: &'a dyn Error) -> Self {
-        Self(err)
+        let format = ChainFormat::default();
+        Self { err, err_static: None, fields: None, format }
+    }
+
+    /// Construct a new `ArrayErrorChain` from a `'static` error, which (unlike
+    /// an error of unknown lifetime) can be downcast: if its type has been
+    /// registered with [`register_error_fields`](crate::register_error_fields),
+    /// its [`ErrorFields`] are also logged by the `slog::KV` implementation.
+    pub fn from_static(err: &'a (dyn Error + 'static)) -> Self {
+        let fields = fields::fields_of(err);
+        Self { err_static: Some(err), fields, ..Self::new(err) }
+    }
+
+    /// Construct a new `ArrayErrorChain` from an error whose own
+    /// [`ErrorFields`] are also logged by the `slog::KV` implementation. See
+    /// [`InlineErrorChain::with_fields`].
+    pub fn with_fields<E: Error + ErrorFields>(err: &'a E) -> Self {
+        Self { fields: Some(err), ..Self::new(err) }
+    }
+
+    chain_format_options! {
+        with_key,
+        emit_root_cause,
+        separator,
+        ///
+        /// When serialized as an array, the marker is its final element.
+        max_depth,
+        prefix_index,
+        suppress_double_speak,
+        max_layer_len,
+        ///
+        /// When serialized, this limits the total length of all elements; no
+        /// further elements are serialized once it has been reached.
+        max_len,
+        escape,
+        errno_names,
     }
 }
 
 impl fmt::Display for ArrayErrorChain<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        InlineErrorChain::new(self.0).fmt(f)
+        InlineErrorChain::with_format(self.err, self.err_static, self.format)
+            .fmt(f)
     }
 }
 
@@ -133,14 +325,9 @@ impl Serialize for ArrayErrorChain<'_> {
     where
         S: serde::Serializer,
     {
-        let mut seq = serializer.serialize_seq(None)?;
-        seq.serialize_element(&format_args!("{}", self.0))?;
-        let mut source = self.0.source();
-        while let Some(cause) = source {
-            seq.serialize_element(&format_args!("{cause}"))?;
-            source = cause.source();
-        }
-        seq.end()
+        let layers = Chain::with_max_depth(self.err, self.format.max_depth)
+            .with_static_root(self.err_static);
+        self.format.serialize_layers(serializer, layers)
     }
 }
 
@@ -148,10 +335,13 @@ impl KV for ArrayErrorChain<'_> {
     #[allow(clippy::useless_conversion)] // see InlineErrorChain's KV impl
     fn serialize(
         &self,
-        _record: &slog::Record,
+        record: &slog::Record,
         serializer: &mut dyn slog::Serializer,
     ) -> slog::Result {
-        serializer.emit_serde("error".into(), self)
+        serializer.emit_serde(self.format.key.into(), self)?;
+        let format = self.format.for_slog();
+        format.emit_root_cause_of(self.err, serializer)?;
+        format.emit_fields_of(self.err, self.fields, record, serializer)
     }
 }
 
@@ -172,7 +362,11 @@ impl SerdeValue for ArrayErrorChain<'_> {
     }
 
     fn to_sendable(&self) -> Box<dyn SerdeValue + Send + 'static> {
-        Box::new(OwnedErrorChain::new(self.0))
+        Box::new(OwnedErrorChain::with_format(
+            self.err,
+            self.err_static,
+            self.format,
+        ))
     }
 
     fn serialize_fallback(
@@ -180,14 +374,158 @@ impl SerdeValue for ArrayErrorChain<'_> {
         key: slog::Key,
         serializer: &mut dyn slog::Serializer,
     ) -> slog::Result<()> {
-        serializer.emit_arguments(key, &format_args!("{self}"))
+        let format = self.format.for_slog();
+        let chain =
+            InlineErrorChain::with_format(self.err, self.err_static, format);
+        serializer.emit_arguments(key, &format_args!("{chain}"))
+    }
+}
+
+/// Adapter for [`Error`]s that provides a [`slog::SerdeValue`] implementation
+/// that serializes the chain of errors as nested objects, each holding its
+/// cause as its `source`:
+///
+/// ```text
+/// {"message": "outer", "source": {"message": "inner", "source": null}}
+/// ```
+///
+/// If the chain is truncated, the innermost object's message describes the
+/// truncation (e.g., `... (3 more causes)`). As with
+/// [`ObjectErrorChain`](crate::ObjectErrorChain), objects for causes that are
+/// [`std::io::Error`]s also include their `kind`, `raw_os_error`, and `errno`,
+/// as does the object for the topmost error if the chain was constructed by
+/// [`LinkedErrorChain::from_static`].
+///
+/// Otherwise, `LinkedErrorChain` behaves like [`ArrayErrorChain`]: its
+/// `Display` implementation and fallback `SerdeValue` format match the
+/// behavior of [`InlineErrorChain`], and the same rendering options are
+/// available.
+pub struct LinkedErrorChain<'a> {
+    err: &'a dyn Error,
+    // `err`, if its type is known to be `'static`; see `from_static()`.
+    err_static: Option<&'a (dyn Error + 'static)>,
+    fields: Option<&'a dyn ErrorFields>,
+    format: ChainFormat,
+}
+
+impl<'a> LinkedErrorChain<'a> {
+    /// Construct a new `LinkedErrorChain` from an error.
+    pub fn new(err: &'a dyn Error) -> Self {
+        let format = ChainFormat { linked: true, ..ChainFormat::default() };
+        Self { err, err_static: None, fields: None, format }
+    }
+
+    /// Construct a new `LinkedErrorChain` from a `'static` error, which (unlike
+    /// an error of unknown lifetime) can be downcast: if it is an
+    /// [`std::io::Error`], its details are serialized like those of its
+    /// causes, and if its type has been registered with
+    /// [`register_error_fields`](crate::register_error_fields), its
+    /// [`ErrorFields`] are also logged by the `slog::KV` implementation.
+    pub fn from_static(err: &'a (dyn Error + 'static)) -> Self {
+        let fields = fields::fields_of(err);
+        Self { err_static: Some(err), fields, ..Self::new(err) }
+    }
+
+    /// Construct a new `LinkedErrorChain` from an error whose own
+    /// [`ErrorFields`] are also logged by the `slog::KV` implementation. See
+    /// [`InlineErrorChain::with_fields`].
+    pub fn with_fields<E: Error + ErrorFields>(err: &'a E) -> Self {
+        Self { fields: Some(err), ..Self::new(err) }
+    }
+
+    chain_format_options! {
+        with_key,
+        emit_root_cause,
+        separator,
+        ///
+        /// When serialized, the marker is the innermost object.
+        max_depth,
+        prefix_index,
+        suppress_double_speak,
+        max_layer_len,
+        ///
+        /// When serialized, this limits the total length of all objects; no
+        /// further objects are serialized once it has been reached.
+        max_len,
+        escape,
+        errno_names,
+    }
+}
+
+impl fmt::Display for LinkedErrorChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        InlineErrorChain::with_format(self.err, self.err_static, self.format)
+            .fmt(f)
+    }
+}
+
+impl Serialize for LinkedErrorChain<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let layers = Chain::with_max_depth(self.err, self.format.max_depth)
+            .with_static_root(self.err_static);
+        self.format.serialize_layers(serializer, layers)
+    }
+}
+
+impl KV for LinkedErrorChain<'_> {
+    #[allow(clippy::useless_conversion)] // see InlineErrorChain's KV impl
+    fn serialize(
+        &self,
+        record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        serializer.emit_serde(self.format.key.into(), self)?;
+        let format = self.format.for_slog();
+        format.emit_root_cause_of(self.err, serializer)?;
+        format.emit_fields_of(self.err, self.fields, record, serializer)
+    }
+}
+
+impl Value for LinkedErrorChain<'_> {
+    fn serialize(
+        &self,
+        _record: &slog::Record,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        serializer.emit_serde(key, self)
+    }
+}
+
+impl SerdeValue for LinkedErrorChain<'_> {
+    fn as_serde(&self) -> &dyn erased_serde::Serialize {
+        self
+    }
+
+    fn to_sendable(&self) -> Box<dyn SerdeValue + Send + 'static> {
+        Box::new(OwnedErrorChain::with_format(
+            self.err,
+            self.err_static,
+            self.format,
+        ))
+    }
+
+    fn serialize_fallback(
+        &self,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result<()> {
+        let format = self.format.for_slog();
+        let chain =
+            InlineErrorChain::with_format(self.err, self.err_static, format);
+        serializer.emit_arguments(key, &format_args!("{chain}"))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests::{ErrorA, ErrorB};
+    use crate::chain::tests::{CyclicError, DeepError};
+    use crate::tests::KeyValueSerializer;
+    use crate::tests::{EmbeddingError, EmbeddingWrapper, ErrorA, ErrorB};
     use slog::{b, record, Level};
     use std::io;
 
@@ -256,6 +594,10 @@ mod tests {
         let err = ErrorB::B(err);
         let chain = OwnedErrorChain::new(&err);
         assert_eq!(chain.to_string(), "error b: error a: test error");
+        assert_eq!(
+            format!("{chain:#}"),
+            "error b\n\nCaused by:\n    0: error a\n    1: test error"
+        );
 
         let mut out = StringSerializer::default();
         chain.serialize_fallback("unused", &mut out).unwrap();
@@ -310,4 +652,355 @@ mod tests {
         Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
         assert_eq!(out.0, r#"["error b","error a","test error"]"#);
     }
+
+    #[test]
+    fn chain_format_options() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+
+        let err = ErrorB::B(ErrorA::A(io::Error::other("test error")));
+
+        let chain = ArrayErrorChain::new(&err)
+            .separator(" <- ")
+            .max_depth(2)
+            .prefix_index(true);
+        assert_eq!(
+            chain.to_string(),
+            "[0] error b <- [1] error a <- ... (1 more cause)"
+        );
+
+        let mut out = StringSerializer::default();
+        chain.serialize_fallback("unused", &mut out).unwrap();
+        assert_eq!(out.0, "[0] error b <- [1] error a <- ... (1 more cause)");
+
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(out.0, r#"["error b","error a","... (1 more cause)"]"#);
+
+        // The owned version produced by `to_sendable()` keeps the options.
+        let mut out = StringSerializer::default();
+        chain.to_sendable().serialize_fallback("unused", &mut out).unwrap();
+        assert_eq!(out.0, "[0] error b <- [1] error a <- ... (1 more cause)");
+
+        let chain = OwnedErrorChain::new(&err)
+            .separator(" <- ")
+            .max_depth(2)
+            .prefix_index(true);
+        assert_eq!(
+            chain.to_string(),
+            "[0] error b <- [1] error a <- ... (1 more cause)"
+        );
+
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(out.0, r#"["error b","error a","... (1 more cause)"]"#);
+    }
+
+    #[test]
+    fn double_speak_suppression() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+
+        let err = EmbeddingWrapper(EmbeddingError::Opening(io::Error::other(
+            "not found",
+        )));
+
+        let chain = ArrayErrorChain::new(&err);
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(
+            out.0,
+            r#"["wrapped: opening file: not found","opening file: not found","not found"]"#
+        );
+
+        let chain = chain.suppress_double_speak(true);
+        assert_eq!(chain.to_string(), "wrapped: opening file: not found");
+
+        let mut out = StringSerializer::default();
+        chain.serialize_fallback("unused", &mut out).unwrap();
+        assert_eq!(out.0, "wrapped: opening file: not found");
+
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(out.0, r#"["wrapped","opening file","not found"]"#);
+
+        let chain = OwnedErrorChain::new(&err).suppress_double_speak(true);
+        assert_eq!(chain.to_string(), "wrapped: opening file: not found");
+        assert_eq!(
+            format!("{chain:#}"),
+            "wrapped\n\nCaused by:\n    0: opening file\n    1: not found"
+        );
+
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(out.0, r#"["wrapped","opening file","not found"]"#);
+    }
+
+    #[test]
+    fn truncated_chains() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+
+        let err =
+            CyclicError { inner: Some(Box::new(CyclicError { inner: None })) };
+        let chain = ArrayErrorChain::new(&err);
+        assert_eq!(chain.to_string(), "cyclic: cyclic: ... (cycle detected)");
+
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(out.0, r#"["cyclic","cyclic","... (cycle detected)"]"#);
+
+        let chain = OwnedErrorChain::new(&err);
+        assert_eq!(chain.to_string(), "cyclic: cyclic: ... (cycle detected)");
+
+        // Reducing the depth of an owned chain accounts for both the layers it
+        // holds and those it never captured.
+        let err = DeepError(1100);
+        let chain = OwnedErrorChain::new(&err);
+        assert!(chain.to_string().ends_with(": ... (76 more causes)"));
+
+        let chain = chain.max_depth(2);
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(
+            out.0,
+            r#"["depth 1100","depth 1099","... (1098 more causes)"]"#
+        );
+    }
+
+    #[test]
+    fn length_limits() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+
+        let err = ErrorB::B(ErrorA::A(io::Error::other("test error")));
+
+        let chain = ArrayErrorChain::new(&err).max_layer_len(6);
+        assert_eq!(chain.to_string(), "err…: err…: tes…");
+
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(out.0, r#"["err…","err…","tes…"]"#);
+
+        let chain = ArrayErrorChain::new(&err).max_len(20);
+        let mut out = StringSerializer::default();
+        chain.serialize_fallback("unused", &mut out).unwrap();
+        assert_eq!(out.0, "error b: error a:…");
+
+        // The total limit applies to the sum of the elements' lengths.
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(out.0, r#"["error b","error a","tes…"]"#);
+
+        let chain = OwnedErrorChain::new(&err).max_layer_len(6).max_len(100);
+        assert_eq!(chain.to_string(), "err…: err…: tes…");
+        assert_eq!(format!("{chain:.6}"), "err…");
+    }
+
+    #[test]
+    fn escaping() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+
+        let err = ErrorA::A(io::Error::other("line 1\n\x1b[31mred\x1b[0m"));
+
+        let chain = ArrayErrorChain::new(&err);
+        assert_eq!(chain.to_string(), "error a: line 1\n\x1b[31mred\x1b[0m");
+
+        let mut out = StringSerializer::default();
+        chain.serialize_fallback("unused", &mut out).unwrap();
+        assert_eq!(out.0, r"error a: line 1\n\u{1b}[31mred\u{1b}[0m");
+
+        // Structured output is left to the serializer to escape.
+        let mut out = StringSerializer::default();
+        Value::serialize(&chain, &dummy_record, "unused", &mut out).unwrap();
+        assert_eq!(out.0, r#"["error a","line 1\n\u001b[31mred\u001b[0m"]"#);
+
+        let mut out = StringSerializer::default();
+        chain.escape(false).serialize_fallback("unused", &mut out).unwrap();
+        assert_eq!(out.0, "error a: line 1\n\x1b[31mred\x1b[0m");
+
+        let chain = OwnedErrorChain::new(&err);
+        assert_eq!(chain.to_string(), "error a: line 1\n\x1b[31mred\x1b[0m");
+
+        let mut out = StringSerializer::default();
+        chain.serialize_fallback("unused", &mut out).unwrap();
+        assert_eq!(out.0, r"error a: line 1\n\u{1b}[31mred\u{1b}[0m");
+
+        let chain = chain.escape(true);
+        assert_eq!(
+            chain.to_string(),
+            r"error a: line 1\n\u{1b}[31mred\u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn root_cause() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let kv = |kv: &dyn KV| {
+            let mut out = KeyValueSerializer::default();
+            kv.serialize(&dummy_record, &mut out).unwrap();
+            out.0.into_iter().skip(1).collect::<Vec<_>>()
+        };
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+
+        let err = ErrorB::B(ErrorA::A(io::Error::other("test error")));
+        let chain = ArrayErrorChain::new(&err).emit_root_cause(true);
+        assert_eq!(
+            kv(&chain),
+            [pair("error.root", "test error"), pair("error.depth", "3")]
+        );
+        let chain = OwnedErrorChain::new(&err).with_key("err");
+        assert_eq!(kv(&chain), []);
+        assert_eq!(
+            kv(&chain.emit_root_cause(true).max_depth(1)),
+            [pair("err.root", "test error"), pair("err.depth", "3")]
+        );
+
+        // Causes beyond those captured by `OwnedErrorChain` still count.
+        let err = DeepError(40);
+        let chain = OwnedErrorChain::new(&err).emit_root_cause(true);
+        assert_eq!(
+            kv(&chain),
+            [pair("error.root", "depth 1"), pair("error.depth", "40")]
+        );
+
+        let err =
+            CyclicError { inner: Some(Box::new(CyclicError { inner: None })) };
+        let chain = OwnedErrorChain::new(&err).emit_root_cause(true);
+        assert_eq!(
+            kv(&chain),
+            [pair("error.root", "cyclic"), pair("error.depth", "2")]
+        );
+
+        // Endless chains are only walked so far.
+        let err = DeepError(usize::MAX);
+        let chain = OwnedErrorChain::new(&err).emit_root_cause(true);
+        assert!(chain.to_string().ends_with(": ... (1+ more causes)"));
+        assert_eq!(
+            kv(&chain),
+            [
+                pair("error.root", &format!("depth {}", usize::MAX - 1023)),
+                pair("error.depth", "1024"),
+            ]
+        );
+    }
+
+    #[test]
+    fn linked_error_chain_formatting() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let serialize = |value: &dyn Value| {
+            let mut out = StringSerializer::default();
+            value.serialize(&dummy_record, "unused", &mut out).unwrap();
+            out.0
+        };
+
+        let err = io::Error::other("test error");
+        let chain = LinkedErrorChain::new(&err);
+        assert_eq!(chain.to_string(), "test error");
+        assert_eq!(
+            serialize(&chain),
+            r#"{"message":"test error","source":null}"#
+        );
+
+        let err = ErrorB::B(ErrorA::A(err));
+        let chain = LinkedErrorChain::new(&err);
+        let expected = concat!(
+            r#"{"message":"error b","source":"#,
+            r#"{"message":"error a","source":"#,
+            r#"{"message":"test error","source":null}}}"#,
+        );
+        assert_eq!(serialize(&chain), expected);
+        assert_eq!(
+            serialize(&OwnedErrorChain::new(&err).linked(true)),
+            expected
+        );
+
+        let owned = chain.to_sendable();
+        let mut out = StringSerializer::default();
+        owned.serialize_fallback("unused", &mut out).unwrap();
+        assert_eq!(out.0, "error b: error a: test error");
+
+        // `io::Error` causes include their kind and OS error code.
+        #[cfg(unix)]
+        {
+            let io_err = io::Error::from_raw_os_error(libc::EACCES);
+            let message = io_err.to_string();
+            let err = ErrorA::A(io_err);
+            let expected = format!(
+                concat!(
+                    r#"{{"message":"error a","source":"#,
+                    r#"{{"message":"{}","kind":"PermissionDenied","#,
+                    r#""raw_os_error":{},"errno":"EACCES","source":null}}}}"#,
+                ),
+                message,
+                libc::EACCES,
+            );
+            assert_eq!(serialize(&LinkedErrorChain::new(&err)), expected);
+            let owned = OwnedErrorChain::new(&err).linked(true);
+            assert_eq!(serialize(&owned), expected);
+        }
+
+        // Truncation is described by the innermost object.
+        let chain = LinkedErrorChain::new(&err).max_depth(1);
+        assert_eq!(
+            serialize(&chain),
+            concat!(
+                r#"{"message":"error b","source":"#,
+                r#"{"message":"... (2 more causes)","source":null}}"#,
+            )
+        );
+        assert_eq!(
+            serialize(&OwnedErrorChain::new(&err).linked(true).max_depth(1)),
+            serialize(&chain)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn static_io_error() {
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let serialize = |value: &dyn Value| {
+            let mut out = StringSerializer::default();
+            value.serialize(&dummy_record, "unused", &mut out).unwrap();
+            out.0
+        };
+
+        let err = io::Error::from_raw_os_error(libc::EACCES);
+        let message = err.to_string();
+
+        // The topmost error can only be downcast if it is known to be
+        // `'static`.
+        assert_eq!(
+            serialize(&LinkedErrorChain::new(&err)),
+            format!(r#"{{"message":"{message}","source":null}}"#)
+        );
+        let expected = format!(
+            concat!(
+                r#"{{"message":"{}","kind":"PermissionDenied","#,
+                r#""raw_os_error":{},"errno":"EACCES","source":null}}"#,
+            ),
+            message,
+            libc::EACCES,
+        );
+        let chain = LinkedErrorChain::from_static(&err);
+        assert_eq!(serialize(&chain), expected);
+        let mut out = StringSerializer::default();
+        slog::Serializer::emit_serde(&mut out, "unused", &*chain.to_sendable())
+            .unwrap();
+        assert_eq!(out.0, expected);
+        let owned = OwnedErrorChain::from_static(&err).linked(true);
+        assert_eq!(serialize(&owned), expected);
+
+        // Arrays of messages have no room for the details.
+        let chain = ArrayErrorChain::from_static(&err);
+        assert_eq!(serialize(&chain), format!(r#"["{message}"]"#));
+        assert_eq!(
+            serialize(&OwnedErrorChain::from_static(&err)),
+            serialize(&chain)
+        );
+    }
 }
@@ -0,0 +1,401 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`ObjectErrorChain`] supports logging error chains as arrays of objects
+//! carrying each cause's message and type, via [`slog::SerdeValue`].
+
+use crate::chain::Chain;
+use crate::fields;
+use crate::fields::ErrorFields;
+use crate::format::chain_format_options;
+use crate::format::ChainFormat;
+use crate::ErrorTypeNames;
+use crate::InlineErrorChain;
+use crate::OwnedErrorChain;
+use serde::Serialize;
+use slog::SerdeValue;
+use slog::Value;
+use slog::KV;
+use std::any;
+use std::error::Error;
+use std::fmt;
+
+/// Adapter for [`Error`]s that provides a [`slog::SerdeValue`] implementation
+/// that serializes the chain of errors as an array of objects, one per error:
+///
+/// ```text
+/// [{"message": "opening config", "type": "my_crate::ConfigError"},
+///  {"message": "file not found", "type": "std::io::error::Error"}]
+/// ```
+///
+/// The types of the causes of an error can't be recovered from its `source()`
+/// chain, so they must be known statically: [`ObjectErrorChain::new`] records
+/// only the type of the topmost error, while [`ObjectErrorChain::from_typed`]
+/// also records the types of any causes reported by [`ErrorTypeNames`] (which
+/// is implemented by the derive macros in this crate). Errors whose type is
+/// unknown have a null `type`. Type names come from [`std::any::type_name`],
+/// and so are best-effort descriptions rather than stable identifiers.
+///
+/// Objects for causes that are [`std::io::Error`]s also include their `kind`
+/// and, where known, their `raw_os_error` and `errno` name, as does the object
+/// for the topmost error if the chain was constructed by
+/// [`ObjectErrorChain::new`].
+///
+/// Like [`ArrayErrorChain`](crate::ArrayErrorChain), `ObjectErrorChain`'s
+/// `Display` implementation and fallback `SerdeValue` format match the
+/// behavior of [`InlineErrorChain`], and the same rendering options are
+/// available.
+pub struct ObjectErrorChain<'a> {
+    err: &'a dyn Error,
+    // `err`, if its type is known to be `'static`, as it is when constructed
+    // by `new()`.
+    err_static: Option<&'a (dyn Error + 'static)>,
+    type_names: Vec<&'static str>,
+    fields: Option<&'a dyn ErrorFields>,
+    format: ChainFormat,
+}
+
+impl<'a> ObjectErrorChain<'a> {
+    /// Construct a new `ObjectErrorChain` from an error, recording the type of
+    /// the error itself. Its type being known, the error can be downcast: if it
+    /// is an [`std::io::Error`], its details are serialized like those of its
+    /// causes, and if its type has been registered with
+    /// [`register_error_fields`](crate::register_error_fields), its
+    /// [`ErrorFields`] are also logged by the `slog::KV` implementation.
+    pub fn new<E: Error + 'static>(err: &'a E) -> Self {
+        let fields = fields::fields_of(err);
+        Self {
+            err_static: Some(err),
+            fields,
+            ..Self::with_type_names(err, vec![any::type_name::<E>()])
+        }
+    }
+
+    /// Construct a new `ObjectErrorChain` from an error, recording the types
+    /// of the error and of its causes given by [`ErrorTypeNames`].
+    pub fn from_typed<E: Error + ErrorTypeNames>(err: &'a E) -> Self {
+        let mut type_names = Vec::new();
+        err.type_names(&mut type_names);
+        Self::with_type_names(err, type_names)
+    }
+
+    /// Construct a new `ObjectErrorChain` from an error, recording the types
+    /// of the error and of its causes given by [`ErrorTypeNames`]. The
+    /// error's own [`ErrorFields`] are also logged by the `slog::KV`
+    /// implementation; see [`InlineErrorChain::with_fields`].
+    pub fn with_fields<E>(err: &'a E) -> Self
+    where
+        E: Error + ErrorTypeNames + ErrorFields,
+    {
+        Self { fields: Some(err), ..Self::from_typed(err) }
+    }
+
+    /// Construct a new `ObjectErrorChain` from an error and the type names of
+    /// the errors in its chain, outermost first.
+    pub fn with_type_names(
+        err: &'a dyn Error,
+        type_names: Vec<&'static str>,
+    ) -> Self {
+        let format = ChainFormat::default();
+        Self { err, err_static: None, type_names, fields: None, format }
+    }
+
+    chain_format_options! {
+        with_key,
+        emit_root_cause,
+        separator,
+        ///
+        /// When serialized, the marker is a final element with a null type.
+        max_depth,
+        prefix_index,
+        suppress_double_speak,
+        max_layer_len,
+        ///
+        /// When serialized, this limits the total length of all elements; no
+        /// further elements are serialized once it has been reached.
+        max_len,
+        escape,
+        errno_names,
+    }
+}
+
+impl fmt::Display for ObjectErrorChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        InlineErrorChain::with_format(self.err, self.err_static, self.format)
+            .fmt(f)
+    }
+}
+
+impl Serialize for ObjectErrorChain<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let layers = Chain::with_max_depth(self.err, self.format.max_depth)
+            .with_static_root(self.err_static);
+        self.format.serialize_layer_objects(
+            serializer,
+            layers,
+            &self.type_names,
+        )
+    }
+}
+
+impl KV for ObjectErrorChain<'_> {
+    #[allow(clippy::useless_conversion)] // see InlineErrorChain's KV impl
+    fn serialize(
+        &self,
+        record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        serializer.emit_serde(self.format.key.into(), self)?;
+        let format = self.format.for_slog();
+        format.emit_root_cause_of(self.err, serializer)?;
+        format.emit_fields_of(self.err, self.fields, record, serializer)
+    }
+}
+
+impl Value for ObjectErrorChain<'_> {
+    fn serialize(
+        &self,
+        _record: &slog::Record,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        serializer.emit_serde(key, self)
+    }
+}
+
+impl SerdeValue for ObjectErrorChain<'_> {
+    fn as_serde(&self) -> &dyn erased_serde::Serialize {
+        self
+    }
+
+    fn to_sendable(&self) -> Box<dyn SerdeValue + Send + 'static> {
+        Box::new(OwnedObjectErrorChain {
+            chain: OwnedErrorChain::with_format(
+                self.err,
+                self.err_static,
+                self.format,
+            ),
+            type_names: self.type_names.clone(),
+        })
+    }
+
+    fn serialize_fallback(
+        &self,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result<()> {
+        let format = self.format.for_slog();
+        let chain =
+            InlineErrorChain::with_format(self.err, self.err_static, format);
+        serializer.emit_arguments(key, &format_args!("{chain}"))
+    }
+}
+
+/// An owned, `'static` version of an [`ObjectErrorChain`].
+///
+/// As with [`OwnedErrorChain`], this type exists primarily to allow
+/// [`ObjectErrorChain`] to implement [`slog::SerdeValue`].
+#[derive(Debug, Clone)]
+pub struct OwnedObjectErrorChain {
+    chain: OwnedErrorChain,
+    type_names: Vec<&'static str>,
+}
+
+impl OwnedObjectErrorChain {
+    /// Construct a new `OwnedObjectErrorChain` from an error, recording the
+    /// type of the error itself. See [`ObjectErrorChain::new`].
+    pub fn new<E: Error + 'static>(err: &E) -> Self {
+        let type_names = vec![any::type_name::<E>()];
+        Self { chain: OwnedErrorChain::from_static(err), type_names }
+    }
+
+    /// Construct a new `OwnedObjectErrorChain` from an error, recording the
+    /// types of the error and of its causes. See
+    /// [`ObjectErrorChain::from_typed`].
+    pub fn from_typed<E: Error + ErrorTypeNames>(err: &E) -> Self {
+        let mut type_names = Vec::new();
+        err.type_names(&mut type_names);
+        Self::with_type_names(err, type_names)
+    }
+
+    /// Construct a new `OwnedObjectErrorChain` from an error and the type
+    /// names of the errors in its chain, outermost first.
+    pub fn with_type_names(
+        err: &dyn Error,
+        type_names: Vec<&'static str>,
+    ) -> Self {
+        Self { chain: OwnedErrorChain::new(err), type_names }
+    }
+
+    /// Set the key used when logging this chain via its `slog::KV`
+    /// implementation (default: `"error"`).
+    pub fn with_key(mut self, key: &'static str) -> Self {
+        self.chain = self.chain.with_key(key);
+        self
+    }
+}
+
+impl fmt::Display for OwnedObjectErrorChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chain.fmt(f)
+    }
+}
+
+impl Serialize for OwnedObjectErrorChain {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.chain.format.serialize_layer_objects(
+            serializer,
+            self.chain.layers(),
+            &self.type_names,
+        )
+    }
+}
+
+impl KV for OwnedObjectErrorChain {
+    #[allow(clippy::useless_conversion)] // see InlineErrorChain's KV impl
+    fn serialize(
+        &self,
+        _record: &slog::Record,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        serializer.emit_serde(self.chain.format.key.into(), self)?;
+        self.chain.emit_root_cause_to(serializer)
+    }
+}
+
+impl Value for OwnedObjectErrorChain {
+    fn serialize(
+        &self,
+        _record: &slog::Record,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result {
+        serializer.emit_serde(key, self)
+    }
+}
+
+impl SerdeValue for OwnedObjectErrorChain {
+    fn as_serde(&self) -> &dyn erased_serde::Serialize {
+        self
+    }
+
+    fn to_sendable(&self) -> Box<dyn SerdeValue + Send + 'static> {
+        Box::new(self.clone())
+    }
+
+    fn serialize_fallback(
+        &self,
+        key: slog::Key,
+        serializer: &mut dyn slog::Serializer,
+    ) -> slog::Result<()> {
+        self.chain.serialize_fallback(key, serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::tests::DeepError;
+    use crate::tests::{EmbeddingError, ErrorA, ErrorB, KeyValueSerializer};
+    use std::io;
+
+    fn json(value: &dyn erased_serde::Serialize) -> String {
+        serde_json::to_string(value).unwrap()
+    }
+
+    #[test]
+    fn object_error_chain_formatting() {
+        let err = ErrorB::B(ErrorA::A(io::Error::other("test error")));
+
+        // Only the type of the topmost error is known.
+        let chain = ObjectErrorChain::new(&err);
+        assert_eq!(chain.to_string(), "error b: error a: test error");
+        assert_eq!(
+            json(&chain),
+            format!(
+                r#"[{{"message":"error b","type":"{}"}},{{"message":"error a","type":null}},{{"message":"test error","type":null}}]"#,
+                any::type_name::<ErrorB>()
+            )
+        );
+
+        let type_names = vec!["b", "a", "io"];
+        let chain = ObjectErrorChain::with_type_names(&err, type_names.clone());
+        let expected = r#"[{"message":"error b","type":"b"},{"message":"error a","type":"a"},{"message":"test error","type":"io"}]"#;
+        assert_eq!(json(&chain), expected);
+        assert_eq!(
+            json(&OwnedObjectErrorChain::with_type_names(&err, type_names)),
+            expected
+        );
+        assert_eq!(json(chain.to_sendable().as_serde()), expected);
+
+        let mut out = KeyValueSerializer::default();
+        chain.serialize_fallback("key", &mut out).unwrap();
+        assert_eq!(
+            out.0,
+            [("key".to_string(), "error b: error a: test error".to_string())]
+        );
+    }
+
+    #[test]
+    fn object_error_chain_options() {
+        let err = DeepError(3);
+        let chain =
+            ObjectErrorChain::with_type_names(&err, vec!["3", "2", "1"])
+                .max_depth(2);
+        let expected = r#"[{"message":"depth 3","type":"3"},{"message":"depth 2","type":"2"},{"message":"... (1 more cause)","type":null}]"#;
+        assert_eq!(json(&chain), expected);
+        assert_eq!(json(chain.to_sendable().as_serde()), expected);
+
+        // Layers removed as double-speak don't shift the types of the others.
+        let err = EmbeddingError::Same(io::Error::other("not found"));
+        let chain = ObjectErrorChain::with_type_names(&err, vec!["same", "io"])
+            .suppress_double_speak(true);
+        assert_eq!(json(&chain), r#"[{"message":"not found","type":"io"}]"#);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn object_error_chain_io_details() {
+        let io_err = io::Error::from_raw_os_error(libc::ENOENT);
+        let message = io_err.to_string();
+        let err = ErrorB::B(ErrorA::A(io_err));
+        let chain = ObjectErrorChain::with_type_names(&err, vec!["b", "a"]);
+        let expected = format!(
+            concat!(
+                r#"[{{"message":"error b","type":"b"}},"#,
+                r#"{{"message":"error a","type":"a"}},"#,
+                r#"{{"message":"{}","type":null,"kind":"NotFound","#,
+                r#""raw_os_error":{},"errno":"ENOENT"}}]"#,
+            ),
+            message,
+            libc::ENOENT,
+        );
+        assert_eq!(json(&chain), expected);
+        assert_eq!(json(chain.to_sendable().as_serde()), expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn object_error_chain_static_io_error() {
+        let err = io::Error::from_raw_os_error(libc::ENOENT);
+        let expected = format!(
+            concat!(
+                r#"[{{"message":"{}","type":"std::io::error::Error","#,
+                r#""kind":"NotFound","raw_os_error":{},"errno":"ENOENT"}}]"#,
+            ),
+            err,
+            libc::ENOENT,
+        );
+        let chain = ObjectErrorChain::new(&err);
+        assert_eq!(json(&chain), expected);
+        assert_eq!(json(chain.to_sendable().as_serde()), expected);
+        assert_eq!(json(&OwnedObjectErrorChain::new(&err)), expected);
+    }
+}
@@ -0,0 +1,99 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`ErrorTypeNames`] reports the concrete types of the layers of an error
+//! chain, which can't be recovered from the `&dyn Error`s yielded by
+//! `source()`.
+
+use std::any;
+
+/// Reports the type names of an error and as many of its causes as are
+/// statically known.
+///
+/// This is implemented by the derive macros in this crate: a derived
+/// implementation records the type of the error itself and then that of its
+/// `#[source]` (or `#[from]`, or `source`) field. If that field's type also
+/// implements `ErrorTypeNames`, its causes are recorded in turn; otherwise,
+/// recording stops there. Variants marked `#[error(transparent)]` record only
+/// the types of their inner error.
+pub trait ErrorTypeNames {
+    /// Push the type name of `self` and of each of its known causes onto
+    /// `type_names`, outermost first.
+    fn type_names(&self, type_names: &mut Vec<&'static str>);
+}
+
+/// Wrapper used by derived [`ErrorTypeNames`] implementations to record the
+/// type names of a source field: `(&Probe(field)).push_type_names(names)`
+/// records all of the type names known by the field's type if it implements
+/// `ErrorTypeNames`, and just its own type name otherwise.
+///
+/// This relies on method resolution preferring [`PushTypeNames`] (implemented
+/// on `Probe` itself) to [`PushTypeName`] (implemented on `&Probe`, and so
+/// only reached by auto-referencing), and only works if the field's type is
+/// concrete.
+#[doc(hidden)]
+pub struct Probe<'a, T: ?Sized>(pub &'a T);
+
+#[doc(hidden)]
+pub trait PushTypeNames {
+    fn push_type_names(&self, type_names: &mut Vec<&'static str>);
+}
+
+impl<T: ErrorTypeNames + ?Sized> PushTypeNames for Probe<'_, T> {
+    fn push_type_names(&self, type_names: &mut Vec<&'static str>) {
+        self.0.type_names(type_names);
+    }
+}
+
+#[doc(hidden)]
+pub trait PushTypeName {
+    fn push_type_names(&self, type_names: &mut Vec<&'static str>);
+}
+
+impl<T: ?Sized> PushTypeName for &Probe<'_, T> {
+    fn push_type_names(&self, type_names: &mut Vec<&'static str>) {
+        type_names.push(any::type_name::<T>());
+    }
+}
+
+#[cfg(test)]
+// The explicit borrows in `(&Probe(..)).push_type_names(..)` are needed to
+// select between the two traits.
+#[allow(clippy::needless_borrow)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    struct Outer(Inner);
+    struct Inner(io::Error);
+
+    impl ErrorTypeNames for Outer {
+        fn type_names(&self, type_names: &mut Vec<&'static str>) {
+            type_names.push(any::type_name::<Self>());
+            (&Probe(&self.0)).push_type_names(type_names);
+        }
+    }
+
+    impl ErrorTypeNames for Inner {
+        fn type_names(&self, type_names: &mut Vec<&'static str>) {
+            type_names.push(any::type_name::<Self>());
+            (&Probe(&self.0)).push_type_names(type_names);
+        }
+    }
+
+    #[test]
+    fn probe_prefers_error_type_names() {
+        let err = Outer(Inner(io::Error::other("inner")));
+        let mut type_names = Vec::new();
+        err.type_names(&mut type_names);
+        assert_eq!(
+            type_names,
+            [
+                any::type_name::<Outer>(),
+                any::type_name::<Inner>(),
+                any::type_name::<io::Error>(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,511 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tests for the output of the derive macros.
+
+#![cfg(feature = "derive")]
+
+use slog::b;
+use slog::record;
+use slog::Level;
+use slog::Value;
+use slog::KV;
+use slog_error_chain::SlogError;
+use slog_error_chain::SlogErrorLevel;
+use slog_error_chain::SlogInlineError;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Serializer that records each emitted key and value as a string.
+#[derive(Default, Debug)]
+struct KeyValueSerializer(Vec<(String, String)>);
+
+impl slog::Serializer for KeyValueSerializer {
+    fn emit_arguments(
+        &mut self,
+        key: slog::Key,
+        val: &fmt::Arguments,
+    ) -> slog::Result {
+        self.0.push((key.to_string(), val.to_string()));
+        Ok(())
+    }
+}
+
+/// Serialize `kv` via its `slog::KV` impl, returning each key/value pair.
+fn serialize_kv(kv: &dyn KV) -> Vec<(String, String)> {
+    let dummy_args = format_args!("dummy");
+    let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+    let mut out = KeyValueSerializer::default();
+    kv.serialize(&dummy_record, &mut out).unwrap();
+    out.0
+}
+
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+enum DefaultKeyError {
+    #[error("default key")]
+    Inner(#[source] io::Error),
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(key = "err")]
+enum CustomKeyError {
+    #[error("custom key")]
+    Inner(#[source] io::Error),
+}
+
+#[test]
+fn inline_key() {
+    let err = DefaultKeyError::Inner(io::Error::other("inner"));
+    assert_eq!(serialize_kv(&err), pairs(&[("error", "default key: inner")]));
+
+    let err = CustomKeyError::Inner(io::Error::other("inner"));
+    assert_eq!(serialize_kv(&err), pairs(&[("err", "custom key: inner")]));
+
+    // An explicit key still takes precedence.
+    let dummy_args = format_args!("dummy");
+    let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+    let mut out = KeyValueSerializer::default();
+    Value::serialize(&err, &dummy_record, "explicit", &mut out).unwrap();
+    assert_eq!(out.0, pairs(&[("explicit", "custom key: inner")]));
+}
+
+#[cfg(feature = "nested-values")]
+mod array {
+    use super::*;
+    use slog_error_chain::ObjectErrorChain;
+    use slog_error_chain::SlogArrayError;
+    use slog_error_chain::SlogObjectError;
+    use std::any::type_name;
+
+    #[derive(Debug, thiserror::Error, SlogArrayError)]
+    #[slog(key = "err")]
+    enum CustomKeyError {
+        #[error("custom key")]
+        Inner(#[source] io::Error),
+    }
+
+    #[derive(Debug, thiserror::Error, SlogObjectError)]
+    enum OuterError {
+        #[error("outer")]
+        Inner(#[from] InnerError),
+        #[error(transparent)]
+        Transparent(InnerError),
+    }
+
+    #[derive(Debug, thiserror::Error, SlogInlineError)]
+    #[error("inner")]
+    struct InnerError {
+        source: io::Error,
+    }
+
+    #[test]
+    fn object_type_names() {
+        let inner = || InnerError { source: io::Error::other("io") };
+        let json = |err: &OuterError| {
+            let chain = ObjectErrorChain::from_typed(err);
+            serde_json::to_value(&chain).unwrap()
+        };
+        let layer = |message: &str, ty: &str| serde_json::json!({ "message": message, "type": ty });
+
+        let err = OuterError::Inner(inner());
+        assert_eq!(
+            json(&err),
+            serde_json::json!([
+                layer("outer", type_name::<OuterError>()),
+                layer("inner", type_name::<InnerError>()),
+                layer("io", type_name::<io::Error>()),
+            ])
+        );
+
+        // A transparent variant is reported as its inner error.
+        let err = OuterError::Transparent(inner());
+        assert_eq!(
+            json(&err),
+            serde_json::json!([
+                layer("inner", type_name::<InnerError>()),
+                layer("io", type_name::<io::Error>()),
+            ])
+        );
+
+        // The derived impls log the same structure.
+        let err = OuterError::Inner(inner());
+        assert_eq!(serde_json::to_value(&err).unwrap()[1]["message"], "inner");
+    }
+
+    #[derive(Debug, thiserror::Error, SlogObjectError)]
+    enum GenericError<'a, E: std::error::Error + 'static> {
+        #[error("reading {0}")]
+        Reading(&'a str, #[source] E),
+    }
+
+    #[test]
+    fn object_generics() {
+        let err = GenericError::Reading(
+            "config",
+            InnerError { source: io::Error::other("io") },
+        );
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!([
+                {
+                    "message": "reading config",
+                    "type": type_name::<GenericError<InnerError>>(),
+                },
+                { "message": "inner", "type": type_name::<InnerError>() },
+                // The causes of a generic source aren't statically known.
+                { "message": "io", "type": null },
+            ])
+        );
+    }
+
+    #[derive(Debug, thiserror::Error, SlogArrayError)]
+    #[error("reading {path}")]
+    struct FieldsError {
+        #[slog(field)]
+        path: String,
+        #[slog(field(rename = "offset"))]
+        pos: u64,
+        #[source]
+        source: io::Error,
+    }
+
+    #[test]
+    fn array_fields() {
+        let err = FieldsError {
+            path: "/a".to_string(),
+            pos: 3,
+            source: io::Error::other("eof"),
+        };
+        assert_eq!(
+            serialize_kv(&err),
+            pairs(&[
+                ("error", "reading /a: eof"),
+                ("error.0.path", "/a"),
+                ("error.0.offset", "3"),
+            ])
+        );
+    }
+
+    #[derive(Debug, thiserror::Error, SlogError)]
+    #[slog(format = "array")]
+    #[error("array")]
+    struct UnifiedArrayError(#[source] io::Error);
+
+    #[derive(Debug, thiserror::Error, SlogError)]
+    #[slog(format = "object")]
+    #[error("object")]
+    struct UnifiedObjectError(#[source] io::Error);
+
+    #[test]
+    fn unified_nested_formats() {
+        let err = UnifiedArrayError(io::Error::other("io"));
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!(["array", "io"])
+        );
+
+        let err = UnifiedObjectError(io::Error::other("io"));
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!([
+                { "message": "object", "type": type_name::<UnifiedObjectError>() },
+                { "message": "io", "type": type_name::<io::Error>() },
+            ])
+        );
+    }
+
+    /// An error with its own `Serialize` impl, e.g., for an HTTP response.
+    #[derive(Debug, thiserror::Error, SlogArrayError)]
+    #[slog(no_serialize)]
+    #[error("bad request")]
+    struct ApiError(#[source] io::Error);
+
+    impl serde::Serialize for ApiError {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str("api error")
+        }
+    }
+
+    /// Serializer that records values emitted via `emit_serde` as JSON.
+    #[derive(Default)]
+    struct JsonSerializer(Vec<(String, String)>);
+
+    impl slog::Serializer for JsonSerializer {
+        fn emit_arguments(
+            &mut self,
+            key: slog::Key,
+            val: &fmt::Arguments,
+        ) -> slog::Result {
+            self.0.push((key.to_string(), val.to_string()));
+            Ok(())
+        }
+
+        fn emit_serde(
+            &mut self,
+            key: slog::Key,
+            value: &dyn slog::SerdeValue,
+        ) -> slog::Result {
+            let json = serde_json::to_string(value.as_serde()).unwrap();
+            self.0.push((key.to_string(), json));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn array_no_serialize() {
+        let err = ApiError(io::Error::other("io"));
+        assert_eq!(serde_json::to_string(&err).unwrap(), r#""api error""#);
+
+        let dummy_args = format_args!("dummy");
+        let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+        let mut out = JsonSerializer::default();
+        KV::serialize(&err, &dummy_record, &mut out).unwrap();
+        assert_eq!(out.0, pairs(&[("error", r#"["bad request","io"]"#)]));
+    }
+
+    #[test]
+    fn array_key() {
+        // `KeyValueSerializer` doesn't support nested values, so we get the
+        // fallback format.
+        let err = CustomKeyError::Inner(io::Error::other("inner"));
+        assert_eq!(serialize_kv(&err), pairs(&[("err", "custom key: inner")]));
+    }
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(key = "err", root_cause)]
+enum RootCauseError {
+    #[error("root cause")]
+    Inner(#[source] io::Error),
+}
+
+#[test]
+fn inline_root_cause() {
+    let err = RootCauseError::Inner(io::Error::other("inner"));
+    assert_eq!(
+        serialize_kv(&err),
+        pairs(&[
+            ("err", "root cause: inner"),
+            ("err.root", "inner"),
+            ("err.depth", "2"),
+        ])
+    );
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(key = "err")]
+enum FieldsError {
+    #[error("opening {path}")]
+    OpeningFile {
+        #[slog(field(rename = "file"))]
+        path: String,
+        #[slog(field)]
+        r#mode: u32,
+        #[source]
+        err: io::Error,
+    },
+    #[error("connecting")]
+    Connecting(#[slog(field(rename = "port"))] u16, #[source] io::Error),
+    #[error("other")]
+    Other(#[source] io::Error),
+}
+
+#[test]
+fn inline_fields() {
+    let err = FieldsError::OpeningFile {
+        path: "/a".to_string(),
+        mode: 0o644,
+        err: io::Error::other("denied"),
+    };
+    assert_eq!(
+        serialize_kv(&err),
+        pairs(&[
+            ("err", "opening /a: denied"),
+            ("err.0.file", "/a"),
+            ("err.0.mode", "420"),
+        ])
+    );
+
+    let err = FieldsError::Connecting(80, io::Error::other("refused"));
+    assert_eq!(
+        serialize_kv(&err),
+        pairs(&[("err", "connecting: refused"), ("err.0.port", "80")])
+    );
+
+    let err = FieldsError::Other(io::Error::other("other"));
+    assert_eq!(serialize_kv(&err), pairs(&[("err", "other: other")]));
+
+    // Fields are only logged by the `KV` impl.
+    let dummy_args = format_args!("dummy");
+    let dummy_record = record!(Level::Info, "dummy", &dummy_args, b!());
+    let mut out = KeyValueSerializer::default();
+    Value::serialize(&err, &dummy_record, "explicit", &mut out).unwrap();
+    assert_eq!(out.0, pairs(&[("explicit", "other: other")]));
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(variant_key = "error_kind")]
+enum VariantKeyError {
+    #[error("opening file")]
+    OpeningFile(#[source] io::Error),
+    #[error(transparent)]
+    Other { source: io::Error },
+}
+
+#[test]
+fn inline_variant_key() {
+    let err = VariantKeyError::OpeningFile(io::Error::other("denied"));
+    assert_eq!(
+        serialize_kv(&err),
+        pairs(&[
+            ("error", "opening file: denied"),
+            ("error_kind", "OpeningFile"),
+        ])
+    );
+
+    let err = VariantKeyError::Other { source: io::Error::other("other") };
+    assert_eq!(
+        serialize_kv(&err),
+        pairs(&[("error", "other"), ("error_kind", "Other")])
+    );
+}
+
+#[derive(Debug, thiserror::Error, SlogError)]
+#[error("inline")]
+struct UnifiedInlineError(#[source] io::Error);
+
+#[derive(Debug, thiserror::Error, SlogError)]
+#[slog(format = "flat", key = "err")]
+#[error("flat")]
+struct UnifiedFlatError(#[source] io::Error);
+
+#[test]
+fn unified_formats() {
+    let err = UnifiedInlineError(io::Error::other("io"));
+    assert_eq!(serialize_kv(&err), pairs(&[("error", "inline: io")]));
+
+    let err = UnifiedFlatError(io::Error::other("io"));
+    assert_eq!(
+        serialize_kv(&err),
+        pairs(&[("err.0", "flat"), ("err.1", "io")])
+    );
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[error("wrapped")]
+struct Wrapped<E>(#[source] E);
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[error("parsing {input:?}")]
+struct ParseError<'a, N: slog::Value> {
+    input: &'a str,
+    #[slog(field)]
+    offset: N,
+    #[source]
+    source: io::Error,
+}
+
+#[test]
+fn inline_generics() {
+    let err = Wrapped(Wrapped(io::Error::other("io")));
+    assert_eq!(serialize_kv(&err), pairs(&[("error", "wrapped: wrapped: io")]));
+
+    let input = String::from("1x");
+    let err =
+        ParseError { input: &input, offset: 1, source: io::Error::other("io") };
+    assert_eq!(
+        serialize_kv(&err),
+        pairs(&[("error", "parsing \"1x\": io"), ("error.0.offset", "1")])
+    );
+}
+
+#[derive(Debug, thiserror::Error, SlogError)]
+#[slog(level = "info")]
+enum LevelError {
+    #[error("client disconnected")]
+    #[slog(level = "warn")]
+    Disconnected,
+    #[error("corrupt database")]
+    #[slog(level = "critical")]
+    Corrupt,
+    #[error("not found")]
+    NotFound,
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[error("internal error")]
+struct DefaultLevelError;
+
+#[test]
+fn derived_levels() {
+    assert_eq!(LevelError::Disconnected.log_level(), Level::Warning);
+    assert_eq!(LevelError::Corrupt.log_level(), Level::Critical);
+    assert_eq!(LevelError::NotFound.log_level(), Level::Info);
+    assert_eq!(DefaultLevelError.log_level(), Level::Error);
+
+    // `log_error!` only needs a logger and the error.
+    let records = Records::default();
+    let log = slog::Logger::root(records.clone(), slog::o!());
+    slog_error_chain::log_error!(log, &LevelError::Disconnected, "serving");
+    slog_error_chain::log_error!(log, &DefaultLevelError, "serving");
+    assert_eq!(
+        records.take(),
+        [
+            (
+                Level::Warning,
+                "serving".to_string(),
+                vec!["error=client disconnected".to_string()]
+            ),
+            (
+                Level::Error,
+                "serving".to_string(),
+                vec!["error=internal error".to_string()]
+            ),
+        ]
+    );
+}
+
+/// The level, message, and key/value pairs (as `key=value` strings) of a
+/// logged record.
+type Logged = (Level, String, Vec<String>);
+
+/// Drain that records each record as a [`Logged`].
+#[derive(Clone, Default)]
+struct Records(Arc<Mutex<Vec<Logged>>>);
+
+impl slog::Drain for Records {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(
+        &self,
+        record: &slog::Record,
+        _values: &slog::OwnedKVList,
+    ) -> Result<(), slog::Never> {
+        let mut out = KeyValueSerializer::default();
+        record.kv().serialize(record, &mut out).unwrap();
+        let kvs = out.0.into_iter().map(|(k, v)| format!("{k}={v}"));
+        self.0.lock().unwrap().push((
+            record.level(),
+            record.msg().to_string(),
+            kvs.collect(),
+        ));
+        Ok(())
+    }
+}
+
+impl Records {
+    fn take(&self) -> Vec<Logged> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
@@ -0,0 +1,25 @@
+use slog_error_chain::SlogInlineError;
+use std::io;
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(double_speak = "deny")]
+enum EnumError {
+    #[error("opening file: {err}")]
+    Opening {
+        #[source]
+        err: io::Error,
+    },
+    #[error("reading file: {}", .0)]
+    Reading(#[from] io::Error),
+    #[error("fine")]
+    Fine { source: io::Error },
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(double_speak = "deny")]
+#[error("writing file: {source:?}")]
+struct StructError {
+    source: io::Error,
+}
+
+fn main() {}
@@ -0,0 +1,37 @@
+#![deny(deprecated)]
+
+use slog_error_chain::SlogInlineError;
+use std::io;
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[error("opening file: {err}")]
+struct DefaultError {
+    #[source]
+    err: io::Error,
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(double_speak = "warn")]
+#[error("reading file: {err}")]
+struct WarnError {
+    #[source]
+    err: io::Error,
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(double_speak = "allow")]
+#[error("opening file: {err}")]
+struct AllowError {
+    #[source]
+    err: io::Error,
+}
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(double_speak = "sometimes")]
+#[error("opening file")]
+struct InvalidError {
+    #[source]
+    err: io::Error,
+}
+
+fn main() {}
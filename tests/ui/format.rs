@@ -0,0 +1,15 @@
+use slog_error_chain::SlogError;
+use slog_error_chain::SlogInlineError;
+use std::io;
+
+#[derive(Debug, thiserror::Error, SlogError)]
+#[slog(format = "nested")]
+#[error("unknown format")]
+struct UnknownFormatError(#[source] io::Error);
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(format = "flat")]
+#[error("fixed format")]
+struct FixedFormatError(#[source] io::Error);
+
+fn main() {}
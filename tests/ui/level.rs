@@ -0,0 +1,15 @@
+use slog_error_chain::SlogInlineError;
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(level = "severe")]
+#[error("unknown level")]
+struct UnknownLevelError;
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+enum VariantAttrError {
+    #[error("key")]
+    #[slog(key = "err")]
+    Key,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use slog_error_chain::SlogError;
+use std::io;
+
+#[derive(Debug, thiserror::Error, SlogError)]
+#[slog(format = "array")]
+#[error("array")]
+struct ArrayError(#[source] io::Error);
+
+#[derive(Debug, thiserror::Error, SlogError)]
+#[slog(format = "object")]
+#[error("object")]
+struct ObjectError(#[source] io::Error);
+
+fn main() {}
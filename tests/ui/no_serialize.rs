@@ -0,0 +1,20 @@
+use slog_error_chain::SlogError;
+use slog_error_chain::SlogInlineError;
+use std::io;
+
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[slog(no_serialize)]
+#[error("inline")]
+struct InlineError(#[source] io::Error);
+
+#[derive(Debug, thiserror::Error, SlogError)]
+#[slog(no_serialize)]
+#[error("default format")]
+struct DefaultFormatError(#[source] io::Error);
+
+#[derive(Debug, thiserror::Error, SlogError)]
+#[slog(format = "flat", no_serialize)]
+#[error("flat")]
+struct FlatError(#[source] io::Error);
+
+fn main() {}
@@ -0,0 +1,14 @@
+use slog_error_chain::SlogError;
+use slog_error_chain::SlogInlineError;
+use std::io;
+
+#[derive(Debug, SlogInlineError)]
+struct NotAnError(io::Error);
+
+#[derive(Debug, SlogError)]
+#[slog(format = "flat")]
+enum Borrowed<'a> {
+    Parsing { input: &'a str },
+}
+
+fn main() {}
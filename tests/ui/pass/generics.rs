@@ -0,0 +1,42 @@
+use slog_error_chain::SlogError;
+use slog_error_chain::SlogInlineError;
+use std::fmt;
+use std::io;
+
+// The derived implementations apply whenever the type is an error.
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[error("wrapped")]
+struct Wrapped<E>(#[source] E);
+
+#[derive(Debug, thiserror::Error, SlogError)]
+#[slog(format = "flat", variant_key = "kind")]
+enum Parsing<'a, T: fmt::Debug> {
+    #[error("parsing {input:?}")]
+    Input {
+        #[slog(field)]
+        input: &'a str,
+        #[source]
+        err: io::Error,
+    },
+    #[error("unexpected value {0:?}")]
+    Value(T),
+}
+
+// Logged fields of generic types must implement `slog::Value`.
+#[derive(Debug, thiserror::Error, SlogInlineError)]
+#[error("at {offset}")]
+struct AtOffset<N: fmt::Display> {
+    #[slog(field)]
+    offset: N,
+    #[source]
+    err: io::Error,
+}
+
+fn assert_value<V: slog::Value + slog::KV>(_: &V) {}
+
+fn main() {
+    assert_value(&Wrapped(io::Error::other("oops")));
+    assert_value(&Wrapped(Wrapped(io::Error::other("oops"))));
+    assert_value(&Parsing::<'_, u8>::Value(1));
+    assert_value(&AtOffset { offset: 3u32, err: io::Error::other("oops") });
+}